@@ -0,0 +1,105 @@
+// cargo bench --bench scan --features mock
+//
+// times the pattern scanner over synthetic memory made to look roughly like osu!'s:
+// many regions of mostly pseudo-random bytes with the default signatures planted near the end
+
+use osu_twitch_bot::osu_memory_reader::mock::MockMemory;
+use osu_twitch_bot::osu_memory_reader::process::ProcessMemory;
+use osu_twitch_bot::osu_memory_reader::scan::Scanner;
+use osu_twitch_bot::osu_memory_reader::signatures::{PatternValue, Signatures};
+use std::time::{Duration, Instant};
+
+const REGION_COUNT: usize = 64;
+const REGION_SIZE: usize = 1024 * 1024;
+const RUNS: u32 = 5;
+
+fn synthetic_memory(signatures: &Signatures) -> MockMemory {
+    let mut memory = MockMemory::new();
+    let mut state: u32 = 0x12345678;
+    for i in 0..REGION_COUNT {
+        let mut region = vec![0_u8; REGION_SIZE];
+        for byte in region.iter_mut() {
+            // xorshift, biased towards zeroes like real heap memory
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *byte = if state.is_multiple_of(4) { 0 } else { (state >> 24) as u8 };
+        }
+        // leave a gap between regions so they stay separate
+        memory.write(0x1000_0000 + i * (REGION_SIZE + 0x1000), &region);
+    }
+
+    for (i, signature) in signatures.patterns.iter().enumerate() {
+        let bytes: Vec<u8> = signature.pattern.iter().map(|v| match v {
+            PatternValue::V(b) => *b,
+            PatternValue::A() => 0x90
+        }).collect();
+        let region = REGION_COUNT - 1 - i % 4;
+        memory.write(0x1000_0000 + region * (REGION_SIZE + 0x1000) + 0x100 * (i + 1), &bytes);
+    }
+    memory
+}
+
+// the scanner this replaced: one pass per pattern, restarting on mismatch
+fn naive_scan(data: &[u8], pattern: &[PatternValue]) -> Option<usize> {
+    (0..data.len()).find(|&i| {
+        i + pattern.len() <= data.len() && pattern.iter().zip(&data[i..]).all(|(v, b)| match v {
+            PatternValue::V(v) => v == b,
+            PatternValue::A() => true
+        })
+    })
+}
+
+fn time(name: &str, mut f: impl FnMut() -> usize) {
+    let mut total = Duration::ZERO;
+    let mut found = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        found = f();
+        total += start.elapsed();
+    }
+    let per_run = total / RUNS;
+    let mb = (REGION_COUNT * REGION_SIZE) as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<24} {:>8.2}ms  {:>8.1} MB/s  ({} patterns found)",
+        name,
+        per_run.as_secs_f64() * 1000.0,
+        mb / per_run.as_secs_f64(),
+        found
+    );
+}
+
+fn main() {
+    let signatures = Signatures::default();
+    let memory = synthetic_memory(&signatures);
+    let regions = memory.regions().unwrap();
+    let patterns: Vec<&[PatternValue]> = signatures.patterns.iter().map(|s| s.pattern.as_slice()).collect();
+    let scanner = Scanner::new(&patterns);
+
+    time("naive, per pattern", || {
+        let mut found = vec![false; patterns.len()];
+        for region in &regions {
+            let mut data = vec![0; region.size];
+            memory.read(region.base, &mut data).unwrap();
+            for (i, pattern) in patterns.iter().enumerate() {
+                if !found[i] && naive_scan(&data, pattern).is_some() {
+                    found[i] = true;
+                }
+            }
+        }
+        found.iter().filter(|f| **f).count()
+    });
+
+    time("single pass", || {
+        let mut pending = vec![true; patterns.len()];
+        let mut buf = Vec::new();
+        regions.iter()
+            .map(|region| scanner.scan_region(&memory, region, &mut pending, &mut buf).unwrap().len())
+            .sum()
+    });
+
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    time(&format!("single pass, {} threads", threads), || {
+        scanner.scan_parallel(&memory, &regions, &vec![true; patterns.len()], threads).len()
+    });
+}
\ No newline at end of file
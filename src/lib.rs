@@ -0,0 +1 @@
+pub mod osu_memory_reader;
\ No newline at end of file
@@ -1,17 +1,53 @@
-mod osu_memory_reader;
-
-use osu_memory_reader::mem::{MemoryClient, MemoryData};
+use osu_twitch_bot::osu_memory_reader::chat::{ChatFilter, ChatRelay};
+use osu_twitch_bot::osu_memory_reader::error::MemoryError;
+use osu_twitch_bot::osu_memory_reader::mem::{MemoryClient, MemoryData};
+use osu_twitch_bot::osu_memory_reader::mode;
+use osu_twitch_bot::osu_memory_reader::pp::{self, PpArgs};
+use osu_twitch_bot::osu_memory_reader::process::{NativeProcess, ProcessMemory, ProcessSelector};
+use osu_twitch_bot::osu_memory_reader::progress::MapProgress;
+use osu_twitch_bot::osu_memory_reader::signatures::Signatures;
+use osu_twitch_bot::osu_memory_reader::snapshot::ReplayMemory;
+use osu_twitch_bot::osu_memory_reader::status::GameEvent;
+use osu_twitch_bot::osu_memory_reader::tourney::TourneyClients;
+use osu_twitch_bot::osu_memory_reader::watcher::MemoryWatcher;
 use irc::client::prelude::*;
+use rosu_pp::model::mode::GameMode;
 use futures::prelude::*;
-use std::io::{Read, Write};
-use std::time::{Duration, Instant};
+use std::fmt;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::fs::File;
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// how often osu! chat is checked for messages to relay
+const CHAT_RELAY_INTERVAL: Duration = Duration::from_secs(2);
+// more than this in one check is dropped rather than flooding twitch chat
+const MAX_RELAYED_PER_CHECK: usize = 3;
+// how often the watcher polls osu! when --poll-interval isn't given
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+// how often to check whether osu! has closed or can be attached to again
+const ATTACH_INTERVAL: Duration = Duration::from_secs(1);
+// after a failed attach, e.g. osu! still starting up, since every attempt is a full memory scan
+const ATTACH_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+// before trying a client that failed to attach again, e.g. a tourney client still starting up
+const CLIENT_RETRY_DELAY: Duration = Duration::from_secs(1);
+// one entry per osu! process, so tourney clients don't replace each other's addresses
+const ADDRESS_CACHE_FILE: &str = "address_cache.txt";
+const NOT_RUNNING_TEXT: &str = "osu! is not running";
+// the details of what went wrong only go to the console
+const COMMAND_FAILED_TEXT: &str = "Couldn't read that from osu!, try again in a moment";
 
 #[derive(Default)]
 struct TwitchConfig {
     username: String,
     oauth_token: String,
-    channel: String
+    channel: String,
+    relay_filter: ChatFilter,
+    process_selector: Option<ProcessSelector>,
+    announce_ur: bool
 }
 
 impl TwitchConfig {
@@ -20,7 +56,7 @@ impl TwitchConfig {
             Ok(f) => f,
             Err(_) => {
                 let mut f = File::create("setup.cfg").or(Err("Unable to create setup.cfg file"))?;
-                f.write("USERNAME=\nOAUTH_TOKEN=\nCHANNEL=".as_bytes()).or(Err("Unable to write to setup.cfg"))?;
+                f.write_all("USERNAME=\nOAUTH_TOKEN=\nCHANNEL=\nRELAY_CHANNELS=\nRELAY_HIGHLIGHTS=\nOSU_PROCESS=\nANNOUNCE_UR=".as_bytes()).or(Err("Unable to write to setup.cfg"))?;
                 return Err("setup.cfg has been created, so now enter info into it".into());
             }
         };
@@ -37,13 +73,31 @@ impl TwitchConfig {
 
         let mut config = TwitchConfig::default();
         for line in String::from_utf8(contents).or(Err("Unable to parse contents of setup.cfg"))?.split("\n") {
-            if line.trim().len() == 0 { continue; }
+            if line.trim().is_empty() { continue; }
 
             let (key, value) = line.split_once("=").ok_or(String::from("Invalid formatting in setup.cfg"))?;
             let key = key.trim();
             let value = value.trim();
+            let list = || value.split(",").map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect();
 
-            if value.len() == 0 {
+            // the relay, process and announcement settings are optional
+            match key.to_uppercase().as_str() {
+                "RELAY_CHANNELS" => { config.relay_filter.channels = list(); continue; },
+                "RELAY_HIGHLIGHTS" => { config.relay_filter.highlights = list(); continue; },
+                "OSU_PROCESS" => {
+                    if !value.is_empty() {
+                        config.process_selector = Some(ProcessSelector::parse(value)?);
+                    }
+                    continue;
+                },
+                "ANNOUNCE_UR" => {
+                    config.announce_ur = matches!(value.to_lowercase().as_str(), "yes" | "true" | "1");
+                    continue;
+                },
+                _ => {}
+            }
+
+            if value.is_empty() {
                 return Err(format!("{key} is missing a value"));
             }
 
@@ -59,87 +113,52 @@ impl TwitchConfig {
     }
 }
 
-macro_rules! transpose_err {
-    ($r:expr) => {
-        match $r {
-            Ok(value) => Ok(value),
-            Err(e) => Err(e.to_string())
-        }
-    };
+#[derive(Debug)]
+enum BotError {
+    Memory(MemoryError),
+    Irc(irc::error::Error)
 }
 
-const MOD_ABBREVIATIONS: [&'static str; 31] = [
-    "NF",
-    "EZ",
-    "TD",
-    "HD",
-    "HR",
-    "SD",
-    "DT",
-    "RX",
-    "HT",
-    "NC",
-    "FL",
-    "AU",
-    "SO",
-    "AP",
-    "PF",
-    "K4",
-    "K5",
-    "K6",
-    "K7",
-    "K8",
-    "FI",
-    "RA",
-    "CN",
-    "TP",
-    "K9",
-    "CO",
-    "K1",
-    "K3",
-    "K2",
-    "V2",
-    "MR"
-];
-
-fn get_mod_string(mods: u32) -> String {
-    if mods == 0 {
-        return "".into();
-    }
-
-    let mut mod_strings: Vec<&str> = vec![];
-    let mut value = mods;
-    let mut nc_enabled = false;
-    let nmods = MOD_ABBREVIATIONS.len();
-    for i in 1..nmods+1 {
-        let flag = 1 << (nmods - i);
-        if value >= flag {
-            value %= flag;
-
-            // don't show dt if nc and dt are present
-            if flag == 512 {
-                nc_enabled = true;
-            } else if flag == 64 && nc_enabled {
-                if value == 0 { break } else { continue }
-            }
-            
-            mod_strings.push(MOD_ABBREVIATIONS[nmods - i]);
-            if value == 0 {
-                break;
-            }
+impl BotError {
+    // whether the bot has to connect to twitch again, otherwise only the command
+    // that ran into the error failed
+    fn needs_reconnect(&self) -> bool {
+        matches!(self, BotError::Irc(_))
+    }
+
+    fn is_process_gone(&self) -> bool {
+        matches!(self, BotError::Memory(e) if e.is_process_gone())
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotError::Memory(e) => write!(f, "{}", e),
+            BotError::Irc(e) => write!(f, "Twitch chat error: {}", e)
         }
     }
+}
 
-    mod_strings.reverse();
-    mod_strings.join("")
+impl From<MemoryError> for BotError {
+    fn from(e: MemoryError) -> Self {
+        BotError::Memory(e)
+    }
+}
+
+impl From<irc::error::Error> for BotError {
+    fn from(e: irc::error::Error) -> Self {
+        BotError::Irc(e)
+    }
 }
 
-async fn get_data(client: &mut MemoryClient) -> Result<MemoryData, String> {
+async fn get_data<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<MemoryData, MemoryError> {
     for i in 0..5 {
         match client.get_memory_data() {
             Ok(data) => return Ok(data),
-            Err(msg) => if i == 4 {
-                return Err(msg);
+            // retrying only helps with reads that raced osu! updating its memory
+            Err(e) => if i == 4 || !e.is_transient() {
+                return Err(e);
             } else {
                 tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                 continue;
@@ -150,59 +169,6 @@ async fn get_data(client: &mut MemoryClient) -> Result<MemoryData, String> {
     unreachable!();
 }
 
-fn get_beatmap(client: &MemoryClient, data: &MemoryData) -> Result<rosu_pp::Beatmap, String> {
-    let mut beatmap_path = client.osu_path.clone();
-    beatmap_path.push(&data.settings.songs_folder);
-    beatmap_path.push(&data.menu.folder);
-    beatmap_path.push(&data.menu.path);
-
-    let beatmap = match rosu_pp::Beatmap::from_path(beatmap_path) {
-        Ok(bm) => bm,
-        Err(_) => {
-            return Err("Failed to parse beatmap file, likely due to permission issues".into())
-        }
-    };
-
-    return Ok(beatmap);
-}
-
-fn parse_acc_arg(arg: &str) -> Result<f64, String> {
-    match (if arg.ends_with("%") { &arg[..arg.len()-1] } else { arg }).parse::<f64>() {
-        Ok(value) => if value < 0.0 || value > 100.0 {
-            Err("Acc must be between 0 and 100".into())
-        } else {
-            Ok(value)
-        },
-        Err(_) => Err("Invalid acc format".into())
-    }
-}
-
-fn parse_mod_arg(arg: &str) -> Result<u32, String> {
-    let mods = if arg.starts_with("+") { &arg[1..] } else { arg };
-    if mods.len() % 2 != 0 {
-        return Err("Invalid mod abbreviation".into());
-    }
-
-    let mods = mods.to_uppercase();
-    let mut mod_flags = 0;
-    for i in 0..(mods.len() / 2) {
-        let abbr = &mods[i*2..i*2+2];
-        if abbr.eq("NM") { continue; }
-        if let Some(i) = MOD_ABBREVIATIONS.iter().position(|&m| m.eq(abbr)) {
-            mod_flags += 1 << i;
-        } else {
-            return Err("Invalid mod abbreviation".into());
-        }
-    }
-
-    // add dt if nc is present without it
-    if mod_flags & 512 != 0 && mod_flags & 64 == 0 {
-        mod_flags += 64;
-    }
-
-    return Ok(mod_flags)
-}
-
 macro_rules! return_err_as_ok {
     ($e:expr) => {
         match $e {
@@ -212,13 +178,25 @@ macro_rules! return_err_as_ok {
     };
 }
 
+// m:ss
+fn format_seconds(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
 macro_rules! maybe_mods {
     ($mods:expr) => {
-        if $mods == 0 { "".into() } else { format!(" +{}", get_mod_string($mods)) }
+        if $mods.is_empty() { "".into() } else { format!(" +{}", $mods) }
     };
 }
 
-async fn get_pp_now_text(client: &mut MemoryClient) -> Result<String, String> {
+macro_rules! maybe_mode {
+    ($mode:expr) => {
+        if $mode == GameMode::Osu { "".into() } else { format!(" in {}", mode::name($mode)) }
+    };
+}
+
+async fn get_pp_now_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
     let data = get_data(client).await?;
     if data.gameplay.is_none() {
         return Ok("Not playing anything".into());
@@ -226,100 +204,355 @@ async fn get_pp_now_text(client: &mut MemoryClient) -> Result<String, String> {
 
     let gameplay = data.gameplay.as_ref().unwrap();
     let stats = &gameplay.stats;
-    let state = rosu_pp::any::ScoreState {
-        n300: stats.hit300 as u32,
-        n_geki: stats.hit_geki as u32,
-        n100: stats.hit100 as u32,
-        n_katu: stats.hit_katu as u32,
-        n50: stats.hit50 as u32,
-        misses: stats.misses as u32,
-        max_combo: stats.max_combo as u32
-    };
-
-    let nhitobjects = (state.n300 + state.n100 + state.n50 + state.misses) as usize;
-    if nhitobjects == 0 {
+    if stats.passed_objects() == 0 {
         return Ok("Current pp count: 0pp".into());
     }
 
-    let beatmap = get_beatmap(client, &data)?;
-    let mut gradual = rosu_pp::Difficulty::new().mods(stats.mods).gradual_performance(&beatmap);
-    match gradual.nth(state, nhitobjects - 1) {
-        Some(attrs) => Ok(format!("Current pp count: {:.2}", attrs.pp())),
-        None => Ok("Current pp count: N/A".into())
+    let beatmap = client.get_beatmap(&data)?;
+    let progress = match MapProgress::new(&beatmap, data.play_time, stats.mods.clock_rate()) {
+        Some(progress) => format!(" | {:.0}% through the map", progress.percent * 100.0),
+        None => "".into()
+    };
+    // kept up to date by the watcher's polls
+    match gameplay.pp {
+        Some(pp) => Ok(format!("Current pp count: {:.2}{}", pp, progress)),
+        None => Ok(format!("Current pp count: N/A{}", progress))
     }
 }
 
-async fn get_pp_text(client: &mut MemoryClient, msg: &str) -> Result<String, String> {
+async fn get_progress_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
     let data = get_data(client).await?;
-    let mut acc = if data.gameplay.is_none() { 100.0 } else { data.gameplay.as_ref().unwrap().stats.accuracy };
-    let mut mods = data.current_mods();
-
-    let mut args = msg.split(" ");
-    let mut acc_specified = false;
-    args.next();
-    for arg in args {
-        if arg.starts_with("+") {
-            mods = return_err_as_ok!(parse_mod_arg(arg));
-        } else {
-            acc = return_err_as_ok!(parse_acc_arg(arg));
-            acc_specified = true;
+    let gameplay = match data.gameplay {
+        Some(ref gameplay) => gameplay,
+        None => return Ok("Not playing anything".into())
+    };
+
+    let beatmap = client.get_beatmap(&data)?;
+    let progress = match MapProgress::new(&beatmap, data.play_time, gameplay.stats.mods.clock_rate()) {
+        Some(progress) => progress,
+        None => return Ok("This map has no objects".into())
+    };
+
+    let mut text = format!(
+        "{} / {} ({:.0}%) | {} left",
+        format_seconds(progress.elapsed),
+        format_seconds(progress.length),
+        progress.percent * 100.0,
+        format_seconds(progress.remaining)
+    );
+    if progress.in_break {
+        text.push_str(" | break");
+    }
+    if progress.kiai {
+        text.push_str(" | kiai");
+    }
+    Ok(text)
+}
+
+async fn get_pp_text<P: ProcessMemory>(client: &mut MemoryClient<P>, msg: &str) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let mut args = return_err_as_ok!(PpArgs::parse(msg.split(" ").skip(1)));
+    let mods = args.mods.unwrap_or(data.current_mods());
+    let mut mode = args.mode.unwrap_or(data.current_mode());
+
+    let beatmap = client.get_beatmap_as(&data, mode)?;
+    if beatmap.mode != mode {
+        if args.mode.is_some() {
+            return Ok(format!("This map can't be played in {}", mode::name(mode)));
         }
+        // a mania map picked with std selected, which osu! plays as mania
+        mode = beatmap.mode;
     }
-    
-    let beatmap = get_beatmap(client, &data)?;
-    let difficulty = rosu_pp::Difficulty::new().mods(mods).calculate(&beatmap);
+    // mods read from osu! are whatever it's playing, only the ones asked for are checked
+    if let Some(mods) = args.mods {
+        return_err_as_ok!(mods.validate(mode as i32));
+    }
+    return_err_as_ok!(args.validate(mode));
+
+    let difficulty = client.get_difficulty(&data, &beatmap, mods);
     // results screen vs other
-    if acc_specified || data.results.is_none() {
-        let pp = difficulty.performance().mods(mods).accuracy(acc).calculate().pp();
-        Ok(format!(
-            "[{}] {:.2} for {:.2}%{}",
-            data.menu.status_name(),
-            pp,
-            acc,
-            maybe_mods!(mods)
-        ))
-    } else {
-        let results = data.results.as_ref().unwrap();
-        let pp = difficulty.performance().mods(mods)
-            .n300(results.hit300 as u32)
-            .n100(results.hit100 as u32)
-            .n50(results.hit50 as u32)
-            .misses(results.misses as u32)
-            .combo(results.max_combo as u32)
-            .calculate().pp();
-        Ok(format!(
-            "[{}] {:.2} for this score{}",
-            data.menu.status_name(),
-            pp,
-            if mods == 0 { "".into() } else { format!(" ({})", get_mod_string(mods)) }
-        ))
-    }
-}
-
-async fn get_np_text(client: &mut MemoryClient) -> Result<String, String> {
+    match data.results {
+        Some(ref results) if !args.has_score() && mode::from_id(results.mode) == mode => {
+            let state = results.score_state();
+            // the whole map has been passed
+            let fc = pp::if_fc(difficulty.clone(), &difficulty, mods, &state, mode);
+            // every mode's judgements, taiko and catch use the geki and katu counts
+            // and mania all of them
+            let pp = difficulty.performance().mods(mods.bits())
+                .state(state)
+                .calculate().pp();
+            Ok(format!(
+                "[{}] {:.2} for this score{}{}, {:.2} if FC",
+                data.menu.status_name(),
+                pp,
+                if mods.is_empty() { "".into() } else { format!(" ({})", mods) },
+                maybe_mode!(mode),
+                fc
+            ))
+        },
+        _ => {
+            // judgement counts decide the acc themselves
+            if args.acc.is_none() && !args.has_counts() {
+                args.acc = Some(match data.gameplay {
+                    Some(ref gameplay) => gameplay.stats.accuracy,
+                    None => 100.0
+                });
+            }
+            let mut performance = args.apply(difficulty.performance().mods(mods.bits()));
+            let state = performance.generate_state();
+            let pp = performance.state(state.clone()).calculate().pp();
+
+            let mut score = vec![format!("{:.2}%", pp::accuracy(&state, mode))];
+            for (count, name) in [(args.n_geki, "320"), (args.n300, "300"), (args.n_katu, "200"), (args.n100, "100"), (args.n50, "50")] {
+                if let Some(count) = count {
+                    score.push(format!("{}x{}", count, name));
+                }
+            }
+            if state.misses > 0 {
+                score.push(format!("{} miss{}", state.misses, if state.misses == 1 { "" } else { "es" }));
+            }
+            if let Some(combo) = args.combo {
+                score.push(format!("{} combo", combo));
+            }
+            Ok(format!(
+                "[{}] {:.2} for {}{}{}",
+                data.menu.status_name(),
+                pp,
+                score.join(", "),
+                maybe_mods!(mods),
+                maybe_mode!(mode)
+            ))
+        }
+    }
+}
+
+async fn get_fc_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let gameplay = match data.gameplay {
+        Some(ref gameplay) => gameplay,
+        None => return Ok("Not playing anything".into())
+    };
+    let stats = &gameplay.stats;
+    if stats.passed_objects() == 0 {
+        return Ok("No hits yet".into());
+    }
+
+    let mode = mode::from_id(stats.mode);
+    let beatmap = client.get_beatmap_as(&data, mode)?;
+    let difficulty = client.get_difficulty(&data, &beatmap, stats.mods);
+    let state = stats.score_state();
+    // the poll above already caught the live pp up to this point, only work it out here
+    // when the play isn't tracked, and off the runtime thread since it goes over the map
+    let passed = match client.get_passed_difficulty(stats.passed_objects()) {
+        Some(passed) => passed,
+        None => {
+            let (beatmap, mods, passed) = (beatmap.clone(), stats.mods, stats.passed_objects());
+            tokio::task::spawn_blocking(move || pp::passed_difficulty(&beatmap, mods, passed))
+                .await
+                .expect("passed difficulty calculation panicked")
+        }
+    };
+
+    let mut text = format!(
+        "{:.2} if FC ({:.2}%)",
+        pp::if_fc(difficulty.clone(), &passed, stats.mods, &state, mode),
+        pp::accuracy(&pp::fc_state(&state, &passed), mode)
+    );
+    // both come from the watcher's polls
+    if let (Some(pp), Some(passed_combo)) = (gameplay.pp, gameplay.passed_combo) {
+        let rest = pp::if_rest_perfect(difficulty, stats.mods, &state, gameplay.combo.max(0) as u32, passed_combo, mode);
+        text = format!("Current: {:.2} | {} | {:.2} if the rest is hit perfectly", pp, text, rest);
+    }
+    Ok(text)
+}
+
+async fn get_np_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
     let data = get_data(client).await?;
     let mods = data.current_mods();
-    let beatmap = get_beatmap(client, &data)?;
-    let difficulty = rosu_pp::Difficulty::new().mods(mods).calculate(&beatmap);
+    let beatmap = client.get_beatmap_as(&data, data.current_mode())?;
+    let difficulty = client.get_difficulty(&data, &beatmap, mods);
 
     Ok(format!(
-        "Now playing: [{}] {} - {} [{}]{} ({:.2}*) by {} | https://osu.ppy.sh/b/{}",
+        "Now playing: [{}] {} - {} [{}]{} ({:.2}*{}) by {} | https://osu.ppy.sh/b/{}",
         data.menu.status_name(),
         data.menu.artist,
         data.menu.title,
         data.menu.difficulty,
         maybe_mods!(mods),
         difficulty.stars(),
+        if beatmap.mode == GameMode::Osu { "".into() } else { format!(" {}", mode::name(beatmap.mode)) },
         data.menu.creator,
         data.menu.beatmap_id
     ))
 }
 
-struct Cooldowns([Instant; 3]);
+async fn get_rank_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let gameplay = match data.gameplay {
+        Some(ref gameplay) => gameplay,
+        None => return Ok("Not playing anything".into())
+    };
+    let leaderboard = match gameplay.leaderboard {
+        Some(ref leaderboard) => leaderboard,
+        None => return Ok("No leaderboard for this map".into())
+    };
+    let player = match leaderboard.local_player {
+        Some(ref player) => player,
+        None => return Ok("Not on the leaderboard".into())
+    };
+
+    let gap = match (leaderboard.next_above(), leaderboard.next_below()) {
+        (Some(above), _) => format!(", {} behind {} (#{})", above.score - player.score, above.name, above.position),
+        (None, Some(below)) => format!(", {} ahead of {} (#{})", player.score - below.score, below.name, below.position),
+        (None, None) => "".into()
+    };
+    Ok(format!("Currently #{} on the leaderboard{}", player.position, gap))
+}
+
+async fn get_keys_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let gameplay = match data.gameplay {
+        Some(ref gameplay) => gameplay,
+        None => return Ok("Not playing anything".into())
+    };
+    let keys = match gameplay.key_overlay {
+        Some(ref keys) => keys,
+        None => return Ok("No key overlay for this mode".into())
+    };
+
+    Ok(format!(
+        "K1: {} | K2: {} | M1: {} | M2: {} | {:.1} kps (peak {:.1})",
+        keys.k1.count,
+        keys.k2.count,
+        keys.m1.count,
+        keys.m2.count,
+        gameplay.kps,
+        gameplay.peak_kps
+    ))
+}
+
+async fn get_profile_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
+    let mut data = get_data(client).await?;
+    if data.user.is_none() && client.find_user_info()? {
+        data = get_data(client).await?;
+    }
+    let user = match data.user {
+        Some(ref user) => user,
+        None => return Ok("Not logged in".into())
+    };
+
+    Ok(format!(
+        "{} ({}) | #{} | {}pp | {:.2}% | {} plays | https://osu.ppy.sh/u/{}",
+        user.name,
+        user.country,
+        user.global_rank,
+        user.pp,
+        user.accuracy,
+        user.play_count,
+        user.user_id
+    ))
+}
+
+async fn get_score_text<P: ProcessMemory>(
+    client: &mut MemoryClient<P>,
+    tourney_clients: &mut TourneyClients<P>
+) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let tournament = match data.tournament {
+        Some(ref tournament) if !tourney_clients.is_empty() => tournament,
+        _ => return Ok("Not in a tourney".into())
+    };
+
+    let scores = tourney_clients.get_scores(tournament);
+    if !scores.is_playing() {
+        return Ok("No map is being played".into());
+    }
+
+    let (one, two) = (scores.team_one_score(), scores.team_two_score());
+    let lead = match one.cmp(&two) {
+        std::cmp::Ordering::Greater => format!("{} leads by {}", tournament.team_one_name, one - two),
+        std::cmp::Ordering::Less => format!("{} leads by {}", tournament.team_two_name, two - one),
+        std::cmp::Ordering::Equal => "Tied".into()
+    };
+    Ok(format!(
+        "{}: {} | {}: {} | {}",
+        tournament.team_one_name,
+        one,
+        tournament.team_two_name,
+        two,
+        lead
+    ))
+}
+
+async fn get_match_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let tournament = match data.tournament {
+        Some(ref tournament) => tournament,
+        None => return Ok("Not in a tourney".into())
+    };
+
+    Ok(format!(
+        "{} {} - {} {} | BO{}",
+        tournament.team_one_name,
+        tournament.left_stars,
+        tournament.right_stars,
+        tournament.team_two_name,
+        tournament.bo
+    ))
+}
+
+async fn get_ur_text<P: ProcessMemory>(client: &mut MemoryClient<P>) -> Result<String, BotError> {
+    let data = get_data(client).await?;
+    let stats = match data.hit_error_stats {
+        Some(stats) => stats,
+        None => return Ok("No hits to get the UR from yet".into())
+    };
+
+    Ok(format!(
+        "{}UR: {:.2} | mean {:+.2}ms (early {:+.2}ms, late {:+.2}ms) | range {:+.0}ms to {:+.0}ms",
+        if data.gameplay.is_some() { "" } else { "Last play " },
+        stats.unstable_rate,
+        stats.mean,
+        stats.mean_early,
+        stats.mean_late,
+        stats.min,
+        stats.max
+    ))
+}
+
+// same commands as in run, minus the permission checks and cooldowns
+async fn get_command_text<P: ProcessMemory>(
+    client: &mut MemoryClient<P>,
+    tourney_clients: &mut TourneyClients<P>,
+    text: &str
+) -> Result<Option<String>, BotError> {
+    let (cmd, _) = text.split_once(" ").unwrap_or((text.trim(), ""));
+    match cmd {
+        "!np" => get_np_text(client).await.map(Some),
+        "!pp" => get_pp_text(client, text).await.map(Some),
+        "!ppnow" => get_pp_now_text(client).await.map(Some),
+        "!lb" | "!rank" => get_rank_text(client).await.map(Some),
+        "!keys" => get_keys_text(client).await.map(Some),
+        "!profile" => get_profile_text(client).await.map(Some),
+        "!score" => get_score_text(client, tourney_clients).await.map(Some),
+        "!match" => get_match_text(client).await.map(Some),
+        "!ur" => get_ur_text(client).await.map(Some),
+        "!progress" => get_progress_text(client).await.map(Some),
+        "!fc" => get_fc_text(client).await.map(Some),
+        _ => Ok(None)
+    }
+}
+
+// commands that get an answer while osu! isn't running
+const COMMANDS: [&str; 12] = ["!np", "!pp", "!ppnow", "!lb", "!rank", "!keys", "!profile", "!score", "!match", "!ur", "!progress", "!fc"];
+// cooldown index and seconds for saying osu! isn't running
+const NOT_RUNNING_COOLDOWN: (usize, u64) = (11, 5);
+
+struct Cooldowns([Instant; 12]);
 
 impl Cooldowns {
     pub fn new() -> Self {
-        return Self([Instant::now().checked_sub(Duration::from_secs(5)).unwrap(); 3])
+        Self([Instant::now().checked_sub(Duration::from_secs(5)).unwrap(); 12])
     }
 
     pub fn can_use(&self, i: usize, cooldown: u64) -> bool {
@@ -335,7 +568,7 @@ fn has_badge(msg: &Message, names: &[&str]) -> bool {
     for tag in msg.tags.as_ref().unwrap() {
         if tag.0.eq("badges") {
             for badge in tag.1.as_ref().unwrap().split(",") {
-                let (name, _) = badge.split_once("/").or(Some((badge, ""))).unwrap();
+                let (name, _) = badge.split_once("/").unwrap_or((badge, ""));
                 for n in names {
                     if name.eq(*n) {
                         return true;
@@ -348,38 +581,144 @@ fn has_badge(msg: &Message, names: &[&str]) -> bool {
     false
 }
 
-async fn run(mem_client: &mut MemoryClient, config: &TwitchConfig) -> Result<(), String> {
+fn relay_chat(mem_client: &mut MemoryClient, relay: &mut ChatRelay, sender: &Sender, channel: &str) -> Result<(), BotError> {
+    // the chat can be mid-update while being read, the next check will pick it up.
+    // if osu! closed, keep_attached will notice
+    let chat = match mem_client.get_chat() {
+        Ok(chat) => chat,
+        Err(e) => {
+            if !e.is_transient() && !e.is_process_gone() {
+                println!("Reading the osu! chat failed: {}", e);
+            }
+            return Ok(());
+        }
+    };
+
+    for (osu_channel, message) in relay.new_messages(&chat).into_iter().take(MAX_RELAYED_PER_CHECK) {
+        sender.send_privmsg(channel, format!("[osu! {}] {}: {}", osu_channel, message.sender, message.content))?;
+    }
+    Ok(())
+}
+
+// posts the UR of every play that reaches the results screen
+async fn announce_ur(osu: &Mutex<OsuState>, sender: &Sender, channel: &str) -> Result<(), BotError> {
+    if let OsuState::Attached { ref watcher, .. } = *osu.lock().await {
+        match get_ur_text(&mut *watcher.client().lock().await).await {
+            Ok(text) => sender.send_privmsg(channel, text)?,
+            Err(e) if e.needs_reconnect() => return Err(e),
+            // keep_attached takes care of osu! closing
+            Err(e) if e.is_process_gone() => {},
+            Err(e) => println!("Announcing the UR failed: {}", e)
+        }
+    }
+    Ok(())
+}
+
+// the next event of the watcher subscribed to, never finishing while there isn't one
+async fn next_event(events: &mut Option<broadcast::Receiver<GameEvent>>) -> Result<GameEvent, RecvError> {
+    match events {
+        Some(events) => events.recv().await,
+        None => future::pending().await
+    }
+}
+
+// osu!'s side of the bot, which comes and goes with osu! while the twitch connection stays up
+enum OsuState {
+    Detached,
+    Attached { watcher: MemoryWatcher, tourney_clients: TourneyClients }
+}
+
+async fn run(osu: &Mutex<OsuState>, config: &TwitchConfig) -> Result<(), BotError> {
     println!("Connecting to server as {} and joining {}...", &config.username, &config.channel);
 
-    let mut twitch_client = transpose_err!(Client::from_config(Config {
+    let mut twitch_client = Client::from_config(Config {
         nickname: Some(config.username.clone()),
         server: Some("irc.chat.twitch.tv".into()),
         channels: vec![config.channel.clone()],
         password: Some(config.oauth_token.clone()),
         ..Config::default()
-    }).await)?;
-    transpose_err!(twitch_client.identify())?;
+    }).await?;
+    twitch_client.identify()?;
 
     let sender = twitch_client.sender();
-    transpose_err!(sender.send_cap_req(&[Capability::Custom("twitch.tv/tags")]))?;
+    sender.send_cap_req(&[Capability::Custom("twitch.tv/tags")])?;
 
     println!(
         "Connected to server as {}. If the bot successfully joins the channel, you should see a message saying so.",
         &config.username,
     );
 
-    let mut stream = transpose_err!(twitch_client.stream())?;
+    let mut stream = twitch_client.stream()?;
     let mut cooldowns = Cooldowns::new();
-    while let Some(msg) = transpose_err!(stream.next().await.transpose())? {
+    let mut relay = (!config.relay_filter.is_empty()).then(|| ChatRelay::new(config.relay_filter.clone()));
+    let mut relay_interval = tokio::time::interval(CHAT_RELAY_INTERVAL);
+    // resubscribed to whenever the bot attaches to osu! again
+    let mut events = None;
+    let mut subscribe_interval = tokio::time::interval(ATTACH_INTERVAL);
+    loop {
+        let msg = tokio::select! {
+            msg = stream.next() => match msg.transpose()? {
+                Some(msg) => msg,
+                None => break
+            },
+            _ = relay_interval.tick(), if relay.is_some() => {
+                if let OsuState::Attached { ref watcher, .. } = *osu.lock().await {
+                    relay_chat(&mut *watcher.client().lock().await, relay.as_mut().unwrap(), &sender, &config.channel)?;
+                }
+                continue;
+            },
+            _ = subscribe_interval.tick(), if config.announce_ur && events.is_none() => {
+                if let OsuState::Attached { ref watcher, .. } = *osu.lock().await {
+                    events = Some(watcher.subscribe());
+                }
+                continue;
+            },
+            event = next_event(&mut events) => {
+                match event {
+                    Ok(GameEvent::PlayCompleted(Some(_))) => announce_ur(osu, &sender, &config.channel).await?,
+                    // the watcher went away with osu!
+                    Err(RecvError::Closed) => events = None,
+                    _ => {}
+                }
+                continue;
+            }
+        };
+
         if let Command::PRIVMSG(ref target, ref text) = msg.command {
             if !text.starts_with("!") { continue; }
-            let (cmd, _) = text.split_once(" ").or(Some((text.trim(), ""))).unwrap();
+            let (cmd, _) = text.split_once(" ").unwrap_or((text.trim(), ""));
+
+            let mut state = osu.lock().await;
+            let (watcher, tourney_clients) = match *state {
+                OsuState::Attached { ref watcher, ref mut tourney_clients } => (watcher, tourney_clients),
+                OsuState::Detached => {
+                    let (i, cooldown) = NOT_RUNNING_COOLDOWN;
+                    if COMMANDS.contains(&cmd) && cooldowns.can_use(i, cooldown) {
+                        sender.send_privmsg(target, NOT_RUNNING_TEXT)?;
+                        cooldowns.reset(i);
+                    }
+                    continue;
+                }
+            };
+            // held for the whole command so the watcher's polls don't interleave with its reads
+            let mut guard = watcher.client().lock().await;
+            let mem_client = &mut *guard;
 
             macro_rules! create_branch {
                 ($i:literal, $c:literal, $f:expr) => {  // index, cooldown (seconds), function
                     {
                         if !cooldowns.can_use($i, $c) { continue; }
-                        transpose_err!(sender.send_privmsg(target, $f.await?))?;
+                        let text = match $f.await {
+                            Ok(text) => text,
+                            // keep_attached detaches once the watcher notices too
+                            Err(e) if e.is_process_gone() => NOT_RUNNING_TEXT.into(),
+                            Err(e) if !e.needs_reconnect() => {
+                                println!("{} failed: {}", cmd, e);
+                                COMMAND_FAILED_TEXT.into()
+                            },
+                            Err(e) => return Err(e)
+                        };
+                        sender.send_privmsg(target, text)?;
                         cooldowns.reset($i);
                     }
                 };
@@ -400,6 +739,14 @@ async fn run(mem_client: &mut MemoryClient, config: &TwitchConfig) -> Result<(),
                 "!np" => create_branch!(0, 5, get_np_text(mem_client)),
                 "!pp" => create_branch!(subonly; 1, 3, get_pp_text(mem_client, text)),
                 "!ppnow" => create_branch!(modonly; 2, 1, get_pp_now_text(mem_client)),
+                "!lb" | "!rank" => create_branch!(3, 5, get_rank_text(mem_client)),
+                "!keys" => create_branch!(4, 3, get_keys_text(mem_client)),
+                "!profile" => create_branch!(5, 5, get_profile_text(mem_client)),
+                "!score" => create_branch!(6, 3, get_score_text(mem_client, tourney_clients)),
+                "!match" => create_branch!(7, 5, get_match_text(mem_client)),
+                "!ur" => create_branch!(8, 3, get_ur_text(mem_client)),
+                "!progress" => create_branch!(9, 3, get_progress_text(mem_client)),
+                "!fc" => create_branch!(10, 3, get_fc_text(mem_client)),
                 _ => {}
             }
         } else if let Command::JOIN(ref channel, _, _) = msg.command {
@@ -410,12 +757,187 @@ async fn run(mem_client: &mut MemoryClient, config: &TwitchConfig) -> Result<(),
     Ok(())
 }
 
-async fn start(config: &TwitchConfig) -> Result<(), String> {
-    println!("Starting up...");
-    let mut mem_client = MemoryClient::open()?;
+#[derive(Default, Clone)]
+struct LaunchOptions {
+    record_dir: Option<PathBuf>,
+    replay_file: Option<PathBuf>,
+    scan_threads: Option<usize>,
+    poll_interval: Option<Duration>
+}
+
+impl LaunchOptions {
+    pub fn from_args() -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} is missing a value", arg));
+            match arg.as_str() {
+                "--record" => options.record_dir = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay_file = Some(PathBuf::from(value()?)),
+                "--scan-threads" => options.scan_threads = Some(value()?.parse().or(Err("--scan-threads must be a number"))?),
+                "--poll-interval" => options.poll_interval = Some(Duration::from_millis(
+                    value()?.parse().ok().filter(|ms| *ms > 0).ok_or("--poll-interval must be a positive number of milliseconds")?
+                )),
+                _ => return Err(format!("Invalid argument '{}'", arg))
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn load_signatures() -> Signatures {
+    match Signatures::load(Path::new("signatures.txt")) {
+        Ok(signatures) => signatures,
+        Err(msg) => {
+            println!("{}. Using the built-in signatures instead", msg);
+            Signatures::default()
+        }
+    }
+}
+
+fn init_client(mem_client: &mut MemoryClient, options: &LaunchOptions, secs: u64, several: bool) -> Result<(), MemoryError> {
+    mem_client.set_signatures(load_signatures());
+    if let Some(threads) = options.scan_threads {
+        mem_client.set_scan_threads(threads);
+    }
+    if let Some(ref record_dir) = options.record_dir {
+        let path = if several {
+            record_dir.join(format!("{}-{}.osusnap", secs, mem_client.process.proc_id))
+        } else {
+            record_dir.join(format!("{}.osusnap", secs))
+        };
+        println!("Recording memory snapshot to {}", path.display());
+        mem_client.record_to(&path)?;
+    }
+    if mem_client.init_cached(Path::new(ADDRESS_CACHE_FILE))? {
+        println!("Reusing addresses of pid {} from {}", mem_client.process.proc_id, ADDRESS_CACHE_FILE);
+    }
+    Ok(())
+}
+
+fn attach(options: &LaunchOptions, selector: Option<&ProcessSelector>) -> Result<(MemoryClient, TourneyClients), MemoryError> {
+    let opened = MemoryClient::open_all(selector)?;
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let client_count = opened.len();
+    let mut clients = Vec::new();
+    let mut failed = Vec::new();
+    let mut last_error = None;
+    for mut mem_client in opened {
+        // a tourney client still starting up gets another go
+        let mut result = init_client(&mut mem_client, options, secs, client_count > 1);
+        if result.as_ref().is_err_and(|e| client_count > 1 && !e.is_process_gone()) {
+            std::thread::sleep(CLIENT_RETRY_DELAY);
+            result = init_client(&mut mem_client, options, secs, true);
+        }
+        // one client failing shouldn't stop the rest, unless it's one of a tourney's (see below)
+        match result.and_then(|()| mem_client.process.info()) {
+            Ok(info) => clients.push((info, mem_client)),
+            Err(e) => {
+                if client_count > 1 {
+                    println!("Skipping osu! pid {}: {}", mem_client.process.proc_id, e);
+                }
+                failed.push(mem_client.process.exe_path.clone());
+                last_error = Some(e);
+            }
+        }
+    }
+    if clients.is_empty() {
+        return Err(last_error.unwrap_or(MemoryError::ProcessNotFound("osu!.exe".into())));
+    }
+
+    let processes = NativeProcess::list("osu!.exe").unwrap_or_default();
+    let (mem_client, tourney_clients) = TourneyClients::split(clients)?;
+    // the teams are split by the spectators' slots, which one missing would shift
+    if !tourney_clients.is_empty() && failed.contains(&mem_client.process.exe_path) {
+        let e = last_error.unwrap_or(MemoryError::ProcessNotFound("osu!.exe".into()));
+        return Err(e.context("Unable to attach to every tourney client"));
+    }
+    if !tourney_clients.is_empty() {
+        println!("Attached to a tourney manager with {} clients", tourney_clients.clients.len());
+    } else if processes.len() > 1 {
+        println!("Found {} osu! processes and attached to pid {}, set OSU_PROCESS in setup.cfg to pick another:", processes.len(), mem_client.process.proc_id);
+        for process in processes {
+            println!("- pid {}: {}", process.proc_id, process.exe_path.display());
+        }
+    }
+    Ok((mem_client, tourney_clients))
+}
+
+// attaches to osu! whenever it's running, and again after it closes or restarts
+async fn keep_attached(osu: Arc<Mutex<OsuState>>, options: LaunchOptions, selector: Option<ProcessSelector>) {
+    let mut last_error = None;
+    loop {
+        let mut state = osu.lock().await;
+        let detached = match *state {
+            OsuState::Attached { ref watcher, ref tourney_clients } if watcher.is_running() => {
+                if tourney_clients.all_alive() {
+                    false
+                } else {
+                    println!("A tourney client has closed, attaching again...");
+                    *state = OsuState::Detached;
+                    true
+                }
+            },
+            OsuState::Attached { .. } => {
+                println!("osu! has closed, waiting for it to start again...");
+                *state = OsuState::Detached;
+                true
+            },
+            OsuState::Detached => true
+        };
+        drop(state);
+
+        let mut wait = ATTACH_INTERVAL;
+        if detached {
+            // scanning osu!'s memory takes a while, which would hold up twitch chat
+            let (attach_options, attach_selector) = (options.clone(), selector.clone());
+            match tokio::task::spawn_blocking(move || attach(&attach_options, attach_selector.as_ref())).await {
+                Ok(Ok((mem_client, tourney_clients))) => {
+                    println!("Attached to osu!");
+                    let watcher = MemoryWatcher::spawn(mem_client, options.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL));
+                    *osu.lock().await = OsuState::Attached { watcher, tourney_clients };
+                    last_error = None;
+                },
+                // osu! not being open yet is expected, anything else is worth saying once
+                Ok(Err(e)) if !e.is_process_gone() => {
+                    let message = e.to_string();
+                    if last_error.as_ref() != Some(&message) {
+                        println!("Unable to attach to osu!: {}. Retrying...", message);
+                        last_error = Some(message);
+                    }
+                    wait = ATTACH_RETRY_INTERVAL;
+                },
+                _ => {}
+            }
+        }
+
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// answers commands typed into stdin from a recorded snapshot, one frame per memory poll
+async fn replay(path: &Path) -> Result<(), MemoryError> {
+    let process = ReplayMemory::open(path)?;
+    println!("Loaded {} frames from {}", process.frame_count(), path.display());
+    let osu_path = process.osu_path.clone();
+    let mut mem_client = MemoryClient::new(process, osu_path);
+    mem_client.set_signatures(load_signatures());
     mem_client.init()?;
+    let mut tourney_clients = TourneyClients { clients: Vec::new() };
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|e| MemoryError::io(Path::new("stdin"), e))?;
+        match get_command_text(&mut mem_client, &mut tourney_clients, line.trim()).await {
+            Ok(Some(text)) => println!("{}", text),
+            Ok(None) => println!("Unknown command"),
+            Err(msg) => println!("{}", msg)
+        };
+        if mem_client.process.remaining_frames() == 0 {
+            println!("Reached the end of the snapshot");
+        }
+    }
 
-    run(&mut mem_client, config).await
+    Ok(())
 }
 
 async fn get_twitch_config() -> TwitchConfig {
@@ -431,9 +953,24 @@ async fn get_twitch_config() -> TwitchConfig {
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
+    let options = match LaunchOptions::from_args() {
+        Ok(options) => options,
+        Err(msg) => return println!("{}", msg)
+    };
+
+    if let Some(ref replay_file) = options.replay_file {
+        if let Err(e) = replay(replay_file).await {
+            println!("{}", e);
+        }
+        return;
+    }
+
     let twitch_config = get_twitch_config().await;
+    println!("Starting up, waiting for osu!...");
+    let osu = Arc::new(Mutex::new(OsuState::Detached));
+    tokio::spawn(keep_attached(osu.clone(), options, twitch_config.process_selector.clone()));
     loop {
-        if let Err(msg) = start(&twitch_config).await {
+        if let Err(msg) = run(&osu, &twitch_config).await {
             println!("{}", msg);
         }
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
@@ -0,0 +1,205 @@
+use crate::osu_memory_reader::mods::Mods;
+
+use rosu_pp::{ Beatmap, Difficulty };
+use rosu_pp::any::DifficultyAttributes;
+use rosu_pp::model::mode::GameMode;
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+// a handful covers going back and forth between a few maps
+pub const BEATMAP_CACHE_SIZE: usize = 8;
+// per map, one for each mod combination asked about
+const DIFFICULTY_CACHE_SIZE: usize = 8;
+
+struct CachedBeatmap {
+    md5: String,
+    // of the file when it was parsed, none when that couldn't be told
+    modified: Option<SystemTime>,
+    beatmap: Arc<Beatmap>,
+    // the std map converted to the other modes it was asked for in
+    converts: Vec<Arc<Beatmap>>,
+    // by mode, mods and clock rate, least recently used first
+    difficulties: Vec<(GameMode, Mods, f64, DifficultyAttributes)>
+}
+
+// parsed beatmaps and their difficulty attributes by md5, so commands don't read and
+// parse the .osu file and work out its difficulty again on every call
+pub struct BeatmapCache {
+    capacity: usize,
+    // least recently used first
+    entries: Vec<CachedBeatmap>
+}
+
+impl BeatmapCache {
+    pub fn new(capacity: usize) -> Self {
+        BeatmapCache {
+            capacity,
+            entries: Vec::new()
+        }
+    }
+
+    // moves the entry to the back, as the most recently used one
+    fn touch(&mut self, md5: &str) -> Option<&mut CachedBeatmap> {
+        let i = self.entries.iter().position(|entry| entry.md5 == md5)?;
+        let entry = self.entries.remove(i);
+        self.entries.push(entry);
+        self.entries.last_mut()
+    }
+
+    // none when it isn't cached or the file changed since, e.g. saved from the editor
+    pub fn get(&mut self, md5: &str, modified: Option<SystemTime>) -> Option<Arc<Beatmap>> {
+        let entry = self.touch(md5)?;
+        if entry.modified != modified {
+            self.entries.pop();
+            return None;
+        }
+        Some(Arc::clone(&entry.beatmap))
+    }
+
+    // not kept without an md5, e.g. read while osu! was still loading the map
+    pub fn insert(&mut self, md5: &str, modified: Option<SystemTime>, beatmap: Beatmap) -> Arc<Beatmap> {
+        if md5.is_empty() {
+            return Arc::new(beatmap);
+        }
+        self.entries.retain(|entry| entry.md5 != md5);
+        if self.entries.len() >= self.capacity {
+            self.entries.remove(0);
+        }
+
+        let beatmap = Arc::new(beatmap);
+        self.entries.push(CachedBeatmap {
+            md5: md5.into(),
+            modified,
+            beatmap: Arc::clone(&beatmap),
+            converts: Vec::new(),
+            difficulties: Vec::new()
+        });
+        beatmap
+    }
+
+    // none when the map can't be played in that mode, only std maps convert to the others.
+    // kept with the map when it's cached
+    pub fn convert(&mut self, md5: &str, beatmap: &Arc<Beatmap>, mode: GameMode) -> Option<Arc<Beatmap>> {
+        if beatmap.mode == mode {
+            return Some(Arc::clone(beatmap));
+        }
+        let entry = self.touch(md5);
+        if let Some(convert) = entry.as_ref().and_then(|entry| entry.converts.iter().find(|convert| convert.mode == mode)) {
+            return Some(Arc::clone(convert));
+        }
+
+        let mut convert = Beatmap::clone(beatmap);
+        if !convert.convert_in_place(mode).success() {
+            return None;
+        }
+        let convert = Arc::new(convert);
+        if let Some(entry) = entry {
+            entry.converts.push(Arc::clone(&convert));
+        }
+        Some(convert)
+    }
+
+    // of a map from get or convert, worked out without being kept when the map isn't cached
+    pub fn difficulty(&mut self, md5: &str, beatmap: &Beatmap, mods: Mods) -> DifficultyAttributes {
+        let clock_rate = mods.clock_rate();
+        let calculate = || Difficulty::new().mods(mods).clock_rate(clock_rate).calculate(beatmap);
+        let entry = match self.touch(md5) {
+            Some(entry) => entry,
+            None => return calculate()
+        };
+
+        let difficulties = &mut entry.difficulties;
+        let key = |(mode, m, rate, _): &(GameMode, Mods, f64, DifficultyAttributes)| {
+            *mode == beatmap.mode && *m == mods && *rate == clock_rate
+        };
+        match difficulties.iter().position(key) {
+            Some(i) => {
+                let difficulty = difficulties.remove(i);
+                difficulties.push(difficulty);
+            },
+            None => {
+                if difficulties.len() >= DIFFICULTY_CACHE_SIZE {
+                    difficulties.remove(0);
+                }
+                difficulties.push((beatmap.mode, mods, clock_rate, calculate()));
+            }
+        }
+        difficulties.last().unwrap().3.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn cached(cache: &BeatmapCache) -> Vec<&str> {
+        cache.entries.iter().map(|entry| entry.md5.as_str()).collect()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_map() {
+        let mut cache = BeatmapCache::new(2);
+        cache.insert("a", None, Beatmap::default());
+        cache.insert("b", None, Beatmap::default());
+        assert!(cache.get("a", None).is_some());
+        cache.insert("c", None, Beatmap::default());
+        assert_eq!(cached(&cache), ["a", "c"]);
+        assert!(cache.get("b", None).is_none());
+
+        // converting and working out the difficulty count as uses too
+        let beatmap = cache.get("a", None).unwrap();
+        cache.difficulty("c", &beatmap, Mods::NONE);
+        assert_eq!(cached(&cache), ["a", "c"]);
+        cache.convert("a", &beatmap, GameMode::Taiko);
+        assert_eq!(cached(&cache), ["c", "a"]);
+    }
+
+    #[test]
+    fn forgets_maps_changed_on_disk() {
+        let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let mut cache = BeatmapCache::new(2);
+        cache.insert("a", Some(saved), Beatmap::default());
+        assert!(cache.get("a", Some(saved)).is_some());
+        assert!(cache.get("a", Some(saved + Duration::from_secs(1))).is_none());
+        // dropped rather than kept for the old time
+        assert!(cache.get("a", Some(saved)).is_none());
+        assert!(cached(&cache).is_empty());
+
+        cache.insert("b", None, Beatmap::default());
+        assert!(cache.get("b", Some(saved)).is_none());
+    }
+
+    #[test]
+    fn skips_maps_without_an_md5() {
+        let mut cache = BeatmapCache::new(2);
+        cache.insert("", None, Beatmap::default());
+        assert!(cached(&cache).is_empty());
+        assert!(cache.get("", None).is_none());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_difficulty() {
+        let mut cache = BeatmapCache::new(2);
+        let beatmap = cache.insert("a", None, Beatmap::default());
+        let mods = [
+            Mods::NONE, Mods::HIDDEN, Mods::HARD_ROCK, Mods::DOUBLE_TIME, Mods::EASY,
+            Mods::HALF_TIME, Mods::FLASHLIGHT, Mods::HIDDEN | Mods::HARD_ROCK
+        ];
+        for m in mods {
+            cache.difficulty("a", &beatmap, m);
+        }
+        assert_eq!(cache.entries[0].difficulties.len(), DIFFICULTY_CACHE_SIZE);
+
+        // NM is used again, so HD is the oldest one when another comes in
+        cache.difficulty("a", &beatmap, Mods::NONE);
+        cache.difficulty("a", &beatmap, Mods::HIDDEN | Mods::DOUBLE_TIME);
+        let difficulties = &cache.entries[0].difficulties;
+        assert_eq!(difficulties.len(), DIFFICULTY_CACHE_SIZE);
+        assert!(!difficulties.iter().any(|(_, m, _, _)| *m == Mods::HIDDEN));
+        assert_eq!(difficulties[difficulties.len() - 2].1, Mods::NONE);
+        assert_eq!(difficulties.last().unwrap().1, Mods::HIDDEN | Mods::DOUBLE_TIME);
+    }
+}
\ No newline at end of file
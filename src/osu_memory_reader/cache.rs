@@ -0,0 +1,148 @@
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::mem::AddressInfo;
+use crate::osu_memory_reader::process::ProcessIdentity;
+
+use std::path::Path;
+
+// enough for a tourney manager and all of its clients
+const MAX_CACHED_PROCESSES: usize = 16;
+
+// addresses found by a previous scan, only valid for the osu! process they were found in.
+// stored as NAME=value lines, numbers in hex, with a blank line between processes
+#[derive(Debug, Clone)]
+pub struct AddressCache {
+    pub identity: ProcessIdentity,
+    pub addresses: AddressInfo
+}
+
+impl AddressCache {
+    pub fn load_all(path: &Path) -> Result<Vec<Self>, MemoryError> {
+        let text = std::fs::read_to_string(path).map_err(|e| MemoryError::io(path, e))?;
+
+        let mut caches = Vec::new();
+        for block in text.split("\n\n") {
+            if block.trim().is_empty() { continue; }
+            caches.push(AddressCache::parse(block).map_err(|message| MemoryError::InvalidFile { path: path.to_path_buf(), message })?);
+        }
+        Ok(caches)
+    }
+
+    pub fn load(path: &Path, identity: &ProcessIdentity) -> Option<Self> {
+        AddressCache::load_all(path).ok()?.into_iter().find(|cache| cache.identity == *identity)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut identity = ProcessIdentity { proc_id: 0, start_time: 0, exe_stamp: 0 };
+        let mut addresses = AddressInfo::default();
+        for line in text.lines() {
+            if line.trim().is_empty() { continue; }
+
+            let (key, value) = line.split_once('=').ok_or(String::from("Invalid formatting"))?;
+            let key = key.trim();
+            let value = u64::from_str_radix(value.trim(), 16).or(Err(format!("{} has an invalid value", key)))?;
+
+            match key {
+                "proc_id" => identity.proc_id = value as u32,
+                "start_time" => identity.start_time = value,
+                "exe_stamp" => identity.exe_stamp = value,
+                name => *addresses.get_mut(name).ok_or(format!("Invalid address cache key '{}'", name))? = value as usize
+            }
+        }
+
+        Ok(AddressCache { identity, addresses })
+    }
+
+    fn format(&self) -> String {
+        let mut text = format!(
+            "proc_id={:X}\nstart_time={:X}\nexe_stamp={:X}\n",
+            self.identity.proc_id,
+            self.identity.start_time,
+            self.identity.exe_stamp
+        );
+        for (name, addr) in AddressInfo::NAMES.iter().zip(self.addresses.values()) {
+            text.push_str(&format!("{}={:X}\n", name, addr));
+        }
+        text
+    }
+
+    // adds this process to the file, replacing an older entry for the same pid and
+    // dropping the oldest entries once there are too many
+    pub fn save(&self, path: &Path) -> Result<(), MemoryError> {
+        let mut caches = AddressCache::load_all(path).unwrap_or_default();
+        caches.retain(|cache| cache.identity.proc_id != self.identity.proc_id);
+        caches.push(self.clone());
+        let skip = caches.len().saturating_sub(MAX_CACHED_PROCESSES);
+
+        let text = caches[skip..].iter().map(|cache| cache.format()).collect::<Vec<String>>().join("\n");
+        std::fs::write(path, text).map_err(|e| MemoryError::io(path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(proc_id: u32, status: usize) -> AddressCache {
+        AddressCache {
+            identity: ProcessIdentity { proc_id, start_time: 0x1D9_5B2C_3A4E_0000 + proc_id as u64, exe_stamp: 0xCBF2_9CE4_8422_2325 },
+            addresses: AddressInfo { status, user_info: 0x1234_ABCD, ..AddressInfo::default() }
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("osu_twitch_bot-{}-{}.txt", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let path = temp_path("round_trip");
+        let (one, two) = (cache(100, 0x0AB0_0010), cache(200, 0x0CD0_0020));
+        one.save(&path).unwrap();
+        two.save(&path).unwrap();
+
+        let loaded = AddressCache::load(&path, &one.identity).unwrap();
+        assert_eq!(loaded.identity, one.identity);
+        assert_eq!(loaded.addresses.values(), one.addresses.values());
+        assert_eq!(AddressCache::load(&path, &two.identity).unwrap().addresses.status, 0x0CD0_0020);
+
+        // another run of the same osu! doesn't match
+        let mut restarted = one.identity.clone();
+        restarted.start_time += 1;
+        assert!(AddressCache::load(&path, &restarted).is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn replaces_entries_of_the_same_process() {
+        let path = temp_path("replace");
+        cache(100, 0x10).save(&path).unwrap();
+        cache(100, 0x20).save(&path).unwrap();
+
+        let caches = AddressCache::load_all(&path).unwrap();
+        assert_eq!(caches.len(), 1);
+        assert_eq!(caches[0].addresses.status, 0x20);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_processes() {
+        let path = temp_path("limit");
+        for pid in 0..MAX_CACHED_PROCESSES as u32 + 3 {
+            cache(pid, 0x10).save(&path).unwrap();
+        }
+
+        let caches = AddressCache::load_all(&path).unwrap();
+        assert_eq!(caches.len(), MAX_CACHED_PROCESSES);
+        assert_eq!(caches[0].identity.proc_id, 3);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(AddressCache::parse("proc_id=1\nnot_an_address=2").is_err());
+        assert!(AddressCache::parse("status=xyz").is_err());
+        assert!(AddressCache::parse("status").is_err());
+    }
+}
\ No newline at end of file
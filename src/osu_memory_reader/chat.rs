@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub time: String,
+    pub sender: String,
+    pub content: String
+}
+
+impl ChatMessage {
+    // osu! keeps the time and sender together as "12:34 name:"
+    pub fn new(time_sender: &str, content: String) -> Self {
+        let (time, sender) = time_sender.trim().split_once(' ').unwrap_or(("", time_sender));
+        ChatMessage {
+            time: time.into(),
+            sender: sender.trim().trim_end_matches(':').into(),
+            content
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatChannel {
+    pub name: String,
+    // only the most recent messages
+    pub messages: Vec<ChatMessage>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChatMemoryData {
+    pub channels: Vec<ChatChannel>
+}
+
+// which osu! chat messages get passed on to twitch
+#[derive(Debug, Clone, Default)]
+pub struct ChatFilter {
+    // every message in these channels, e.g. #multiplayer
+    pub channels: Vec<String>,
+    // messages in any channel containing one of these words, e.g. the streamer's name
+    pub highlights: Vec<String>
+}
+
+impl ChatFilter {
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty() && self.highlights.is_empty()
+    }
+
+    pub fn matches(&self, channel: &str, message: &ChatMessage) -> bool {
+        let content = message.content.to_lowercase();
+        self.channels.iter().any(|c| c.eq_ignore_ascii_case(channel))
+            || self.highlights.iter().any(|h| content.contains(&h.to_lowercase()))
+    }
+}
+
+// picks out the messages that arrived since the last poll
+#[derive(Debug)]
+pub struct ChatRelay {
+    filter: ChatFilter,
+    last_seen: HashMap<String, Option<ChatMessage>>,
+    started: bool
+}
+
+impl ChatRelay {
+    pub fn new(filter: ChatFilter) -> Self {
+        ChatRelay { filter, last_seen: HashMap::new(), started: false }
+    }
+
+    // new messages that pass the filter as (channel, message). the first call only
+    // takes note of what's already there, so old chat isn't repeated on startup
+    pub fn new_messages(&mut self, chat: &ChatMemoryData) -> Vec<(String, ChatMessage)> {
+        let mut relayed = Vec::new();
+        for channel in &chat.channels {
+            let new = match self.last_seen.get(&channel.name) {
+                // osu! drops old messages, so look for where the last seen one is now
+                Some(Some(last)) => match channel.messages.iter().rposition(|m| m == last) {
+                    Some(i) => &channel.messages[i + 1..],
+                    None => &channel.messages[..]
+                },
+                // a channel joined after startup is all new
+                Some(None) | None if self.started => &channel.messages[..],
+                _ => &[]
+            };
+
+            relayed.extend(
+                new.iter()
+                    .filter(|m| self.filter.matches(&channel.name, m))
+                    .map(|m| (channel.name.clone(), m.clone()))
+            );
+            self.last_seen.insert(channel.name.clone(), channel.messages.last().cloned());
+        }
+
+        self.started = true;
+        relayed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(time_sender: &str, content: &str) -> ChatMessage {
+        ChatMessage::new(time_sender, content.into())
+    }
+
+    fn chat(channels: &[(&str, &[ChatMessage])]) -> ChatMemoryData {
+        ChatMemoryData {
+            channels: channels.iter()
+                .map(|(name, messages)| ChatChannel { name: name.to_string(), messages: messages.to_vec() })
+                .collect()
+        }
+    }
+
+    fn relay() -> ChatRelay {
+        ChatRelay::new(ChatFilter { channels: vec![String::from("#multiplayer")], highlights: vec![String::from("Streamer")] })
+    }
+
+    #[test]
+    fn splits_time_and_sender() {
+        let m = message("12:34 peppy:", "hi");
+        assert_eq!((m.time.as_str(), m.sender.as_str(), m.content.as_str()), ("12:34", "peppy", "hi"));
+        let m = message(" 01:02 some player: ", "gl");
+        assert_eq!((m.time.as_str(), m.sender.as_str()), ("01:02", "some player"));
+    }
+
+    #[test]
+    fn first_poll_skips_old_chat() {
+        let mut relay = relay();
+        let old = [message("12:00 a:", "before startup")];
+        assert!(relay.new_messages(&chat(&[("#multiplayer", &old)])).is_empty());
+
+        let new = [old[0].clone(), message("12:01 b:", "after")];
+        let relayed = relay.new_messages(&chat(&[("#multiplayer", &new)]));
+        assert_eq!(relayed, vec![(String::from("#multiplayer"), new[1].clone())]);
+    }
+
+    #[test]
+    fn relays_each_message_once() {
+        let mut relay = relay();
+        relay.new_messages(&chat(&[("#multiplayer", &[])]));
+
+        let first = [message("12:00 a:", "one"), message("12:00 b:", "two")];
+        assert_eq!(relay.new_messages(&chat(&[("#multiplayer", &first)])).len(), 2);
+        assert!(relay.new_messages(&chat(&[("#multiplayer", &first)])).is_empty());
+
+        // osu! dropped the oldest message and a new one came in
+        let second = [first[1].clone(), message("12:01 a:", "three")];
+        let relayed = relay.new_messages(&chat(&[("#multiplayer", &second)]));
+        assert_eq!(relayed, vec![(String::from("#multiplayer"), second[1].clone())]);
+    }
+
+    #[test]
+    fn new_channels_are_all_new() {
+        let mut relay = relay();
+        relay.new_messages(&chat(&[]));
+        let messages = [message("12:00 a:", "hi")];
+        assert_eq!(relay.new_messages(&chat(&[("#multiplayer", &messages)])).len(), 1);
+    }
+
+    #[test]
+    fn only_relays_what_the_filter_matches() {
+        let mut relay = relay();
+        relay.new_messages(&chat(&[("#osu", &[]), ("#multiplayer", &[])]));
+
+        let osu = [message("12:00 a:", "hello"), message("12:00 b:", "hi streamer")];
+        let multi = [message("12:00 c:", "ready?")];
+        let relayed = relay.new_messages(&chat(&[("#osu", &osu), ("#Multiplayer", &multi)]));
+        assert_eq!(relayed, vec![
+            (String::from("#osu"), osu[1].clone()),
+            (String::from("#Multiplayer"), multi[0].clone())
+        ]);
+        assert!(ChatFilter::default().is_empty());
+    }
+}
\ No newline at end of file
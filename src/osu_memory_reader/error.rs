@@ -0,0 +1,83 @@
+use std::fmt;
+use std::path::{ Path, PathBuf };
+
+#[derive(Debug)]
+pub enum MemoryError {
+    ProcessNotFound(String),
+    AccessDenied { pid: u32, message: String },
+    // the process was attached to but has since exited
+    ProcessGone(u32),
+    PatternNotFound(Vec<String>),
+    // what was null, like the name of a chain that didn't resolve
+    NullPointer(String),
+    ReadFailed { addr: usize, message: String },
+    InvalidUtf16(usize),
+    // a value that can't be right, like a list longer than any osu! makes
+    InvalidData(String),
+    Io { path: PathBuf, message: String },
+    // a signatures, address cache or snapshot file that doesn't parse
+    InvalidFile { path: PathBuf, message: String },
+    // any other failure of the os apis
+    Os(String),
+    // what was being read when the error happened
+    Context { context: String, cause: Box<MemoryError> }
+}
+
+impl MemoryError {
+    pub fn io(path: &Path, e: std::io::Error) -> Self {
+        MemoryError::Io { path: path.to_path_buf(), message: e.to_string() }
+    }
+
+    pub fn context(self, context: impl Into<String>) -> Self {
+        MemoryError::Context { context: context.into(), cause: Box::new(self) }
+    }
+
+    // the error without any context around it
+    pub fn root(&self) -> &MemoryError {
+        match self {
+            MemoryError::Context { cause, .. } => cause.root(),
+            e => e
+        }
+    }
+
+    // osu! isn't there anymore, so only attaching again can help
+    pub fn is_process_gone(&self) -> bool {
+        matches!(self.root(), MemoryError::ProcessGone(_) | MemoryError::ProcessNotFound(_))
+    }
+
+    // reads that can fail while osu! is updating what's being read, and likely work on a retry
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.root(),
+            MemoryError::NullPointer(_) | MemoryError::ReadFailed { .. } | MemoryError::InvalidUtf16(_) | MemoryError::InvalidData(_)
+        )
+    }
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryError::ProcessNotFound(name) => write!(f, "Failed to find process {}", name),
+            MemoryError::AccessDenied { pid, message } => write!(f, "Access to process {} was denied: {}", pid, message),
+            MemoryError::ProcessGone(pid) => write!(f, "Process {} has exited", pid),
+            MemoryError::PatternNotFound(names) => write!(f, "Unable to find patterns: {}", names.join(", ")),
+            MemoryError::NullPointer(what) => write!(f, "{} is null", what),
+            MemoryError::ReadFailed { addr, message } => write!(f, "Failed to read address {:X}: {}", addr, message),
+            MemoryError::InvalidUtf16(addr) => write!(f, "Invalid UTF-16 string at {:X}", addr),
+            MemoryError::InvalidData(message) => write!(f, "{}", message),
+            MemoryError::Io { path, message } => write!(f, "Unable to access {}: {}", path.display(), message),
+            MemoryError::InvalidFile { path, message } => write!(f, "{}: {}", path.display(), message),
+            MemoryError::Os(message) => write!(f, "{}", message),
+            MemoryError::Context { context, cause } => write!(f, "{}: {}", context, cause)
+        }
+    }
+}
+
+impl std::error::Error for MemoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MemoryError::Context { cause, .. } => Some(cause.as_ref()),
+            _ => None
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,90 @@
+// summary of a play's hit errors. osu! records them in map time, these are in real time
+// (what osu! calls the converted UR) so DT and HT plays compare with nomod ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitErrorStats {
+    pub unstable_rate: f64,
+    pub mean: f64,
+    // mean of the early (negative) and late (positive) hits, 0 when there are none
+    pub mean_early: f64,
+    pub mean_late: f64,
+    pub min: f64,
+    pub max: f64,
+    pub count: usize
+}
+
+impl HitErrorStats {
+    // none until there's at least one hit
+    pub fn from_errors(errors: &[i32], clock_rate: f64) -> Option<Self> {
+        if errors.is_empty() {
+            return None;
+        }
+
+        let errors: Vec<f64> = errors.iter().map(|e| *e as f64 / clock_rate).collect();
+        let count = errors.len();
+        let mean = errors.iter().sum::<f64>() / count as f64;
+        let variance = errors.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / count as f64;
+
+        let side_mean = |early: bool| {
+            let side: Vec<f64> = errors.iter().copied().filter(|e| if early { *e < 0.0 } else { *e >= 0.0 }).collect();
+            if side.is_empty() { 0.0 } else { side.iter().sum::<f64>() / side.len() as f64 }
+        };
+
+        Some(HitErrorStats {
+            unstable_rate: variance.sqrt() * 10.0,
+            mean,
+            mean_early: side_mean(true),
+            mean_late: side_mean(false),
+            min: errors.iter().copied().fold(f64::INFINITY, f64::min),
+            max: errors.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            count
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn summarizes_hit_errors() {
+        let stats = HitErrorStats::from_errors(&[-10, 10, -20, 20], 1.0).unwrap();
+        // standard deviation of 250^0.5 ms
+        assert_close(stats.unstable_rate, 250f64.sqrt() * 10.0);
+        assert_close(stats.mean, 0.0);
+        assert_close(stats.mean_early, -15.0);
+        assert_close(stats.mean_late, 15.0);
+        assert_eq!((stats.min, stats.max, stats.count), (-20.0, 20.0, 4));
+
+        // every hit on the same side, with 0 counting as late
+        let stats = HitErrorStats::from_errors(&[5, 5, 0, 6], 1.0).unwrap();
+        assert_close(stats.mean, 4.0);
+        assert_eq!((stats.mean_early, stats.min, stats.max), (0.0, 0.0, 6.0));
+        assert_close(stats.mean_late, 4.0);
+    }
+
+    #[test]
+    fn converts_to_real_time() {
+        let nomod = HitErrorStats::from_errors(&[-9, 3, 12], 1.0).unwrap();
+        for clock_rate in [1.5, 0.75] {
+            let stats = HitErrorStats::from_errors(&[-9, 3, 12], clock_rate).unwrap();
+            assert_close(stats.unstable_rate, nomod.unstable_rate / clock_rate);
+            assert_close(stats.mean, 2.0 / clock_rate);
+            assert_close(stats.mean_early, -9.0 / clock_rate);
+            assert_close(stats.mean_late, 7.5 / clock_rate);
+            assert_close(stats.min, -9.0 / clock_rate);
+            assert_close(stats.max, 12.0 / clock_rate);
+        }
+        // DT's 60ms of map time are 40ms as played
+        assert_close(HitErrorStats::from_errors(&[-60, 60], 1.5).unwrap().unstable_rate, 400.0);
+        assert_close(HitErrorStats::from_errors(&[-60, 60], 0.75).unwrap().unstable_rate, 800.0);
+    }
+
+    #[test]
+    fn needs_hits() {
+        assert_eq!(HitErrorStats::from_errors(&[], 1.0), None);
+    }
+}
\ No newline at end of file
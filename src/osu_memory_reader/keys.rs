@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::time::{ Duration, Instant };
+
+// how far back keypresses are counted for kps
+const KPS_WINDOW: Duration = Duration::from_secs(1);
+// polls closer together than this don't give a useful rate yet
+const KPS_MIN_SPAN: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyState {
+    pub pressed: bool,
+    pub count: i32
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KeyOverlayMemoryData {
+    pub k1: KeyState,
+    pub k2: KeyState,
+    pub m1: KeyState,
+    pub m2: KeyState
+}
+
+impl KeyOverlayMemoryData {
+    pub fn total(&self) -> i32 {
+        self.k1.count + self.k2.count + self.m1.count + self.m2.count
+    }
+}
+
+// keys per second from the key overlay totals of successive polls
+#[derive(Debug, Default)]
+pub struct KpsTracker {
+    samples: VecDeque<(Instant, i32)>,
+    kps: f64,
+    peak: f64
+}
+
+impl KpsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, now: Instant, total: i32) -> f64 {
+        // the counts go back to 0 on a retry or a new map
+        if self.samples.back().is_some_and(|(_, last)| total < *last) {
+            self.reset();
+        }
+        self.samples.push_back((now, total));
+
+        // keep one sample from before the window so it stays fully covered
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= KPS_WINDOW {
+            self.samples.pop_front();
+        }
+
+        let (start, first) = self.samples[0];
+        let elapsed = now.duration_since(start);
+        if elapsed < KPS_MIN_SPAN {
+            return self.kps;
+        }
+
+        self.kps = (total - first) as f64 / elapsed.as_secs_f64();
+        self.peak = self.peak.max(self.kps);
+        self.kps
+    }
+
+    pub fn kps(&self) -> f64 {
+        self.kps
+    }
+
+    // highest kps since the current map or attempt started
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.kps = 0.0;
+        self.peak = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // polls every 100ms from start, with the total going up by the given keys per poll
+    fn poll(tracker: &mut KpsTracker, start: Instant, polls: std::ops::Range<u64>, total: &mut i32, keys: i32) -> f64 {
+        let mut kps = 0.0;
+        for i in polls {
+            *total += keys;
+            kps = tracker.update(start + Duration::from_millis(i * 100), *total);
+        }
+        kps
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn measures_a_steady_rate() {
+        let start = Instant::now();
+        let mut tracker = KpsTracker::new();
+        let mut total = 0;
+        // too soon to tell
+        assert_eq!(poll(&mut tracker, start, 0..2, &mut total, 1), 0.0);
+        assert_close(poll(&mut tracker, start, 2..50, &mut total, 1), 10.0);
+        assert_close(tracker.kps(), 10.0);
+        // only what the window covers is kept
+        assert!(tracker.samples.len() <= 12);
+    }
+
+    #[test]
+    fn starts_over_when_the_total_goes_back() {
+        let start = Instant::now();
+        let mut tracker = KpsTracker::new();
+        let mut total = 100;
+        poll(&mut tracker, start, 0..20, &mut total, 2);
+        assert_close(tracker.peak(), 20.0);
+
+        // a retry resets the overlay
+        total = 0;
+        assert_eq!(poll(&mut tracker, start, 20..21, &mut total, 0), 0.0);
+        assert_eq!(tracker.peak(), 0.0);
+        assert_close(poll(&mut tracker, start, 21..40, &mut total, 1), 10.0);
+        assert_close(tracker.peak(), 10.0);
+    }
+
+    #[test]
+    fn keeps_the_peak_past_the_window() {
+        let start = Instant::now();
+        let mut tracker = KpsTracker::new();
+        let mut total = 0;
+        poll(&mut tracker, start, 0..20, &mut total, 3);
+        // slowing down to a key every half second
+        let mut kps = 0.0;
+        for i in 20..60 {
+            if i % 5 == 0 {
+                total += 1;
+            }
+            kps = tracker.update(start + Duration::from_millis(i * 100), total);
+        }
+        assert!(kps <= 2.5, "{}", kps);
+        assert_close(tracker.peak(), 30.0);
+    }
+}
\ No newline at end of file
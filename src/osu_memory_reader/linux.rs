@@ -0,0 +1,209 @@
+use libc::{ c_void, iovec, pid_t, process_vm_readv };
+
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::process::MemoryRegion;
+
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+pub type ProcessHandle = pid_t;
+
+#[derive(Debug, Default)]
+pub struct ModuleInfo {
+    pub base: usize,
+    pub size: usize
+}
+
+// wine reports windows paths (C:\...\osu!.exe) in cmdline, so compare only the file name
+fn exe_file_name(path: &str) -> &str {
+    path.rsplit(['\\', '/']).next().unwrap_or(path)
+}
+
+// reading from /proc fails with permission denied when ptrace access isn't allowed
+fn os_error(hproc: ProcessHandle, message: &str, e: std::io::Error) -> MemoryError {
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied => MemoryError::AccessDenied { pid: hproc as u32, message: e.to_string() },
+        _ => MemoryError::Os(format!("{}: {}", message, e))
+    }
+}
+
+fn proc_matches(pid: &str, proc_name: &str) -> bool {
+    if let Ok(comm) = fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        if comm.trim_end().eq(proc_name) {
+            return true;
+        }
+    }
+
+    match fs::read(format!("/proc/{}/cmdline", pid)) {
+        Ok(cmdline) => {
+            let arg0 = cmdline.split(|c| *c == 0).next().unwrap_or(&[]);
+            exe_file_name(&String::from_utf8_lossy(arg0)).eq(proc_name)
+        },
+        Err(_) => false
+    }
+}
+
+pub unsafe fn close_handle(_handle: ProcessHandle) -> Result<(), MemoryError> {
+    // pids don't hold any resources
+    Ok(())
+}
+
+pub unsafe fn open_process(pid: u32) -> Result<ProcessHandle, MemoryError> {
+    if !Path::new(&format!("/proc/{}/maps", pid)).exists() {
+        return Err(MemoryError::ProcessGone(pid));
+    }
+    Ok(pid as ProcessHandle)
+}
+
+pub unsafe fn process_alive(hproc: ProcessHandle) -> bool {
+    Path::new(&format!("/proc/{}", hproc)).exists()
+}
+
+pub unsafe fn find_procs(proc_name: &str) -> Result<Vec<u32>, MemoryError> {
+    let entries = match fs::read_dir("/proc") {
+        Ok(e) => e,
+        Err(e) => return Err(MemoryError::Os(format!("Failed to iterate processes: {}", e)))
+    };
+
+    let mut pids = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let pid = match file_name.to_str() {
+            Some(s) => s,
+            None => continue
+        };
+        let pid_value = match pid.parse::<u32>() {
+            Ok(v) => v,
+            Err(_) => continue
+        };
+
+        if proc_matches(pid, proc_name) {
+            pids.push(pid_value);
+        }
+    }
+    pids.sort_unstable();
+    Ok(pids)
+}
+
+struct MapsEntry {
+    start: usize,
+    end: usize,
+    readable: bool,
+    path: String
+}
+
+fn read_maps(hproc: ProcessHandle) -> Result<Vec<MapsEntry>, MemoryError> {
+    let maps = match fs::read_to_string(format!("/proc/{}/maps", hproc)) {
+        Ok(s) => s,
+        Err(e) => return Err(os_error(hproc, "Failed to read memory maps", e))
+    };
+    let invalid = |line: &str| MemoryError::Os(format!("Invalid maps entry: {}", line));
+
+    let mut entries = Vec::new();
+    for line in maps.lines() {
+        // start-end perms offset dev inode [path]
+        let mut parts = line.splitn(6, ' ');
+        let range = parts.next().unwrap_or("");
+        let perms = parts.next().unwrap_or("");
+        let path = parts.nth(3).unwrap_or("").trim_start();
+
+        let (start, end) = range.split_once('-').ok_or(invalid(line))?;
+        let start = usize::from_str_radix(start, 16).or(Err(invalid(line)))?;
+        let end = usize::from_str_radix(end, 16).or(Err(invalid(line)))?;
+
+        entries.push(MapsEntry {
+            start,
+            end,
+            readable: perms.starts_with('r'),
+            path: path.into()
+        });
+    }
+    Ok(entries)
+}
+
+pub unsafe fn get_proc_info(hproc: ProcessHandle) -> Result<(String, ModuleInfo), MemoryError> {
+    // wine maps the PE image from its unix path, which also gives us the osu! folder
+    let mut exe_path: Option<String> = None;
+    let mut module_info = ModuleInfo::default();
+    for entry in read_maps(hproc)? {
+        if !exe_file_name(&entry.path).eq_ignore_ascii_case("osu!.exe") {
+            continue;
+        }
+
+        match exe_path {
+            None => {
+                exe_path = Some(entry.path);
+                module_info.base = entry.start;
+                module_info.size = entry.end - entry.start;
+            },
+            Some(_) => module_info.size = entry.end.max(module_info.base + module_info.size) - module_info.base
+        }
+    }
+
+    match exe_path {
+        Some(path) => Ok((path, module_info)),
+        None => Err(MemoryError::Os(String::from("Unable to find module")))
+    }
+}
+
+// start time in clock ticks since boot, which together with the pid identifies a process
+pub unsafe fn process_start_time(hproc: ProcessHandle) -> Result<u64, MemoryError> {
+    let stat = match fs::read_to_string(format!("/proc/{}/stat", hproc)) {
+        Ok(s) => s,
+        Err(e) => return Err(os_error(hproc, "Failed to read process stat", e))
+    };
+
+    // the command name can contain spaces, so count fields from after its closing parenthesis
+    stat.rsplit_once(')')
+        .and_then(|(_, fields)| fields.split_whitespace().nth(19))
+        .and_then(|v| v.parse::<u64>().ok())
+        .ok_or(MemoryError::Os(String::from("Failed to parse process start time")))
+}
+
+pub unsafe fn readable_regions(hproc: ProcessHandle) -> Result<Vec<MemoryRegion>, MemoryError> {
+    Ok(
+        read_maps(hproc)?.into_iter()
+            .filter(|entry| entry.readable && !entry.path.eq("[vvar]") && !entry.path.eq("[vsyscall]"))
+            .map(|entry| MemoryRegion { base: entry.start, size: entry.end - entry.start })
+            .collect()
+    )
+}
+
+pub unsafe fn read_address(hproc: ProcessHandle, addr: usize, buf: &mut [u8], size: usize) -> Result<(), MemoryError> {
+    let local = iovec { iov_base: buf.as_mut_ptr().cast::<c_void>(), iov_len: size };
+    let remote = iovec { iov_base: addr as *mut c_void, iov_len: size };
+    let nb = process_vm_readv(hproc, &local, 1, &remote, 1, 0);
+    if nb < 0 {
+        let e = std::io::Error::last_os_error();
+        return Err(match e.kind() {
+            std::io::ErrorKind::PermissionDenied => MemoryError::AccessDenied { pid: hproc as u32, message: e.to_string() },
+            _ => MemoryError::ReadFailed { addr, message: e.to_string() }
+        });
+    }
+    if nb as usize != size {
+        return Err(MemoryError::ReadFailed { addr, message: format!("partial read of {} bytes", nb) });
+    }
+    Ok(())
+}
+
+// maps a path reported by osu! onto the unix filesystem, resolving drive letters
+// through the wine prefix that contains the osu! folder
+pub fn native_path(osu_path: &Path, path: &str) -> PathBuf {
+    let bytes = path.as_bytes();
+    if bytes.len() < 2 || bytes[1] != b':' {
+        let mut native = osu_path.to_path_buf();
+        native.push(path.replace('\\', "/"));
+        return native;
+    }
+
+    let drive = path[..2].to_lowercase();
+    let rest = path[2..].trim_start_matches(['\\', '/']).replace('\\', "/");
+    for prefix in osu_path.ancestors() {
+        let device = prefix.join("dosdevices").join(&drive);
+        if device.exists() {
+            return device.join(rest);
+        }
+    }
+
+    PathBuf::from(path)
+}
\ No newline at end of file
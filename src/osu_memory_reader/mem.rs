@@ -1,26 +1,54 @@
 #[cfg(target_os = "windows")]
-use crate::osu_memory_reader::win::*;
+use crate::osu_memory_reader::win::native_path;
+#[cfg(target_os = "linux")]
+use crate::osu_memory_reader::linux::native_path;
 
-#[cfg(target_os = "windows")]
-use windows::Win32::Foundation::HANDLE;
-#[cfg(target_os = "windows")]
-use windows::Win32::System::Memory::{ PAGE_NOACCESS, PAGE_GUARD };
-#[cfg(target_os = "windows")]
-use windows::Win32::System::ProcessStatus::MODULEINFO;
+use crate::osu_memory_reader::beatmaps::{ BeatmapCache, BEATMAP_CACHE_SIZE };
+use crate::osu_memory_reader::cache::AddressCache;
+use crate::osu_memory_reader::chat::{ ChatChannel, ChatMemoryData, ChatMessage };
+use crate::osu_memory_reader::country::country_code;
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::hits::HitErrorStats;
+use crate::osu_memory_reader::keys::{ KeyOverlayMemoryData, KeyState, KpsTracker };
+use crate::osu_memory_reader::mode;
+use crate::osu_memory_reader::mods::Mods;
+use crate::osu_memory_reader::pp::LivePp;
+use crate::osu_memory_reader::process::{ MemoryRegion, NativeProcess, ProcessMemory, ProcessSelector };
+use crate::osu_memory_reader::read::{ MemoryBlock, MemoryReader };
+use crate::osu_memory_reader::scan::{ PatternHit, Scanner };
+use crate::osu_memory_reader::signatures::{ PatternValue, Signatures };
+use crate::osu_memory_reader::snapshot::SnapshotWriter;
+use crate::osu_memory_reader::status::OsuStatus;
+use crate::osu_memory_reader::tourney::TourneyClientData;
 
-use crate::osu_memory_reader::read::MemoryReader;
+use rosu_pp::Beatmap;
+use rosu_pp::any::{ DifficultyAttributes, ScoreState };
+use rosu_pp::model::mode::GameMode;
 
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
 
-enum PatternValue {
-    V(u8), // value
-    A() // any
-}
+// a sane upper bound on hits in a single map
+const MAX_HIT_ERRORS: usize = 1_000_000;
 
-// make it easier to write out the patterns
-use PatternValue::{ V, A };
+// more entries than osu! ever shows means the list address is garbage
+const MAX_LEADERBOARD_ENTRIES: usize = 100;
 
-#[derive(Debug)]
+// the chat keeps a lot of history, only the newest messages of each channel are read
+const MAX_CHAT_MESSAGES: usize = 50;
+const MAX_CHAT_CHANNELS: usize = 100;
+
+// user_info isn't waited for in init, a missing one is scanned for at most this often
+const USER_INFO_RESCAN: Duration = Duration::from_secs(30);
+
+// how many bytes around each pattern hit go into a snapshot
+const SNAPSHOT_HIT_WINDOW: usize = 0x40;
+
+#[derive(Debug, Default)]
 pub struct SettingsMemoryData {
     pub songs_folder: String,
     pub skin_folder: String,
@@ -42,10 +70,10 @@ pub struct TournamentMemoryData {
     pub ipc_base_addr: u32
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct ResultsMemoryData {
     pub player_name: String,
-    pub mods: u32,
+    pub mods: Mods,
     pub mode: i32,
     pub max_combo: i16,
     pub score: i32,
@@ -58,7 +86,34 @@ pub struct ResultsMemoryData {
     pub accuracy: f64
 }
 
-#[derive(Debug)]
+impl ResultsMemoryData {
+    pub fn score_state(&self) -> ScoreState {
+        ScoreState {
+            max_combo: self.max_combo as u32,
+            n_geki: self.hit_geki as u32,
+            n_katu: self.hit_katu as u32,
+            n300: self.hit300 as u32,
+            n100: self.hit100 as u32,
+            n50: self.hit50 as u32,
+            misses: self.misses as u32
+        }
+    }
+
+    // hit objects the judgements so far are for. taiko has no 50s, and in catch the 50s
+    // and katus are tiny droplets, which aren't counted as objects
+    pub fn passed_objects(&self) -> usize {
+        let [geki, n300, katu, n100, n50, misses] = [
+            self.hit_geki, self.hit300, self.hit_katu, self.hit100, self.hit50, self.misses
+        ].map(|n| n.max(0) as usize);
+        match mode::from_id(self.mode) {
+            GameMode::Osu => n300 + n100 + n50 + misses,
+            GameMode::Taiko | GameMode::Catch => n300 + n100 + misses,
+            GameMode::Mania => geki + n300 + katu + n100 + n50 + misses
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct MenuMemoryData {
     pub game_mode: i32,
     pub plays: i32,
@@ -82,7 +137,7 @@ pub struct MenuMemoryData {
     pub ranked_status: i32,
     pub md5: String,
     pub object_count: i32,
-    pub mods: u32
+    pub mods: Mods
 }
 
 impl MenuMemoryData {
@@ -102,6 +157,42 @@ impl MenuMemoryData {
 }
 
 #[derive(Debug)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: i32,
+    pub combo: i16,
+    pub max_combo: i16,
+    pub mods: Mods,
+    pub position: i32,
+    pub is_passing: bool
+}
+
+#[derive(Debug)]
+pub struct LeaderboardMemoryData {
+    pub entries: Vec<LeaderboardEntry>,
+    pub local_player: Option<LeaderboardEntry>
+}
+
+impl LeaderboardMemoryData {
+    // 1 is first place
+    pub fn local_rank(&self) -> Option<i32> {
+        self.local_player.as_ref().map(|p| p.position)
+    }
+
+    // the entry one place above the local player
+    pub fn next_above(&self) -> Option<&LeaderboardEntry> {
+        let rank = self.local_rank()?;
+        self.entries.iter().find(|e| e.position == rank - 1)
+    }
+
+    // the entry one place below the local player
+    pub fn next_below(&self) -> Option<&LeaderboardEntry> {
+        let rank = self.local_rank()?;
+        self.entries.iter().find(|e| e.position == rank + 1)
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct GameplayMemoryData {
     pub stats: ResultsMemoryData,
     pub retries: i32,
@@ -109,24 +200,61 @@ pub struct GameplayMemoryData {
     pub combo: i16,
     pub hp_smooth: f64,
     pub hp: f64,
-    // pub leaderboard: u32,
-    // keyoverlayarrayaddr
+    // none when the map has no leaderboard or it's turned off
+    pub leaderboard: Option<LeaderboardMemoryData>,
+    // none when the key overlay isn't available, e.g. in mania
+    pub key_overlay: Option<KeyOverlayMemoryData>,
+    // these are filled in from the previous polls by MemoryClient::get_memory_data.
+    // pp and passed_combo are none when the beatmap couldn't be loaded
+    pub kps: f64,
+    pub peak_kps: f64,
+    pub pp: Option<f64>,
+    // max combo of the objects passed so far, see pp::if_rest_perfect
+    pub passed_combo: Option<u32>
+}
+
+#[derive(Debug)]
+pub struct UserMemoryData {
+    pub name: String,
+    pub user_id: i32,
+    pub country: &'static str,
+    pub global_rank: i32,
+    pub pp: i32,
+    pub accuracy: f64,
+    pub play_count: i32,
+    pub level: f32,
+    pub play_mode: i32
 }
 
 #[derive(Debug)]
 pub struct MemoryData {
-    pub status: u32,
-    pub chat_status: i8,
+    pub status: OsuStatus,
+    pub chat_visible: bool,
     pub play_time: i32,
     pub settings: SettingsMemoryData,
     pub tournament: Option<TournamentMemoryData>,
     pub results: Option<ResultsMemoryData>,
     pub menu: MenuMemoryData,
-    pub gameplay: Option<GameplayMemoryData>
+    pub gameplay: Option<GameplayMemoryData>,
+    // of the current play, or the last one after it ended. filled in by MemoryClient::get_memory_data
+    pub hit_error_stats: Option<HitErrorStats>,
+    // the logged in player, none when logged out or user_info wasn't found
+    pub user: Option<UserMemoryData>
 }
 
 impl MemoryData {
-    pub fn current_mods(&self) -> u32 {
+    // the ruleset of the play or score being shown, otherwise the one picked in the menus
+    pub fn current_mode(&self) -> GameMode {
+        if let Some(ref results) = self.results {
+            mode::from_id(results.mode)
+        } else if let Some(ref gameplay) = self.gameplay {
+            mode::from_id(gameplay.stats.mode)
+        } else {
+            mode::from_id(self.menu.game_mode)
+        }
+    }
+
+    pub fn current_mods(&self) -> Mods {
         if let Some(ref results) = self.results {
             results.mods
         } else if let Some(ref gameplay) = self.gameplay {
@@ -138,7 +266,7 @@ impl MemoryData {
 }
 
 #[derive(Debug)]
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct AddressInfo {
     pub status: usize,
     pub settings_class: usize,
@@ -152,121 +280,405 @@ pub struct AddressInfo {
     pub user_info: usize
 }
 
-pub struct MemoryClient {
-    pub proc_id: u32,
-    hproc: HANDLE,
-    pub hinfo: MODULEINFO,
-    pub is_open: bool,
+impl AddressInfo {
+    pub const NAMES: [&'static str; 10] = [
+        "status",
+        "settings_class",
+        "base",
+        "menu_mods",
+        "play_time",
+        "chat_checker",
+        "skin_data",
+        "rulesets",
+        "chat_area",
+        "user_info"
+    ];
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        AddressInfo::NAMES.iter().position(|n| *n == name).map(|i| self.values()[i])
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut usize> {
+        match name {
+            "status" => Some(&mut self.status),
+            "settings_class" => Some(&mut self.settings_class),
+            "base" => Some(&mut self.base),
+            "menu_mods" => Some(&mut self.menu_mods),
+            "play_time" => Some(&mut self.play_time),
+            "chat_checker" => Some(&mut self.chat_checker),
+            "skin_data" => Some(&mut self.skin_data),
+            "rulesets" => Some(&mut self.rulesets),
+            "chat_area" => Some(&mut self.chat_area),
+            "user_info" => Some(&mut self.user_info),
+            _ => None
+        }
+    }
+
+    pub fn values(&self) -> [usize; 10] {
+        [
+            self.status,
+            self.settings_class,
+            self.base,
+            self.menu_mods,
+            self.play_time,
+            self.chat_checker,
+            self.skin_data,
+            self.rulesets,
+            self.chat_area,
+            self.user_info
+        ]
+    }
+}
+
+pub struct MemoryClient<P: ProcessMemory = NativeProcess> {
+    pub process: P,
     addresses: AddressInfo,
-    pub osu_path: PathBuf
+    signatures: Signatures,
+    scan_threads: usize,
+    pub osu_path: PathBuf,
+    recorder: Option<RefCell<SnapshotWriter>>,
+    // chains resolved during the current poll, none outside of one
+    chain_cache: RefCell<Option<HashMap<String, usize>>>,
+    kps: KpsTracker,
+    hit_error_stats: Option<HitErrorStats>,
+    beatmaps: BeatmapCache,
+    live_pp: Option<LivePp>,
+    // map, mode, mods and last play_time of a play whose beatmap failed to load, so it's
+    // only tried again on a retry or another play rather than on every poll
+    live_pp_failed: Option<(String, GameMode, Mods, i32)>,
+    user_info_scanned: Option<Instant>,
+    // whether the current poll is a background one, like the watcher's or the chat relay's,
+    // rather than a command's
+    watching: bool
 }
 
 impl MemoryClient {
-    pub fn open() -> Result<Self, String> {
-        unsafe {
-            let proc_id = find_proc("osu!.exe")?;
-            let hproc = open_process(proc_id)?;
-            let (exe_path, mod_info) = get_proc_info(hproc)?;
-            let mut osu_path = PathBuf::from(exe_path);
-            osu_path.pop();
-
-            Ok(MemoryClient {
-                proc_id,
-                hproc,
-                hinfo: mod_info,
-                is_open: true,
-                addresses: AddressInfo::default(),
-                osu_path
-            })
+    pub fn open(selector: &ProcessSelector) -> Result<Self, MemoryError> {
+        let process = NativeProcess::open("osu!.exe", selector)?;
+        let mut osu_path = process.exe_path.clone();
+        osu_path.pop();
+
+        Ok(MemoryClient::new(process, osu_path))
+    }
+
+    // one client for every running osu! the selector picks, or every one without a selector,
+    // for tourneys where each spectator is its own process
+    pub fn open_all(selector: Option<&ProcessSelector>) -> Result<Vec<Self>, MemoryError> {
+        Ok(
+            NativeProcess::open_selected("osu!.exe", selector)?.into_iter()
+                .map(|process| {
+                    let mut osu_path = process.exe_path.clone();
+                    osu_path.pop();
+                    MemoryClient::new(process, osu_path)
+                })
+                .collect()
+        )
+    }
+
+    // reuses the addresses cached from an earlier run when osu! hasn't restarted since
+    // and they still check out, otherwise does a full scan and caches its result.
+    // returns whether the cached addresses were used
+    pub fn init_cached(&mut self, cache_path: &Path) -> Result<bool, MemoryError> {
+        // without an identity nothing can be reused or cached, which only costs a scan
+        let identity = self.process.identity().ok();
+        if let Some(cache) = identity.as_ref().and_then(|identity| AddressCache::load(cache_path, identity)) {
+            self.addresses = cache.addresses;
+            if self.addresses_valid() {
+                if self.recorder.is_some() {
+                    self.record_pattern_hits(&self.process.regions()?);
+                }
+                self.finish_snapshot_frame()?;
+                return Ok(true);
+            }
+            self.addresses = AddressInfo::default();
         }
+
+        self.init()?;
+        // a cache that can't be written only costs a scan next time
+        if let Some(identity) = identity {
+            let _ = AddressCache { identity, addresses: self.addresses.clone() }.save(cache_path);
+        }
+        Ok(false)
     }
+}
 
-    fn close(&mut self) -> Result<(), String> {
-        if !self.is_open {
-            return Err(String::from("MemoryClient has already been closed"));
+impl<P: ProcessMemory> MemoryClient<P> {
+    pub fn new(process: P, osu_path: PathBuf) -> Self {
+        MemoryClient {
+            process,
+            addresses: AddressInfo::default(),
+            signatures: Signatures::default(),
+            scan_threads: 1,
+            osu_path,
+            recorder: None,
+            chain_cache: RefCell::new(None),
+            kps: KpsTracker::new(),
+            hit_error_stats: None,
+            beatmaps: BeatmapCache::new(BEATMAP_CACHE_SIZE),
+            live_pp: None,
+            live_pp_failed: None,
+            user_info_scanned: None,
+            watching: false
         }
-        unsafe { close_handle(self.hproc)? }
-        self.is_open = false;
+    }
+
+    pub fn set_signatures(&mut self, signatures: Signatures) {
+        self.signatures = signatures;
+    }
+
+    // dumps every pattern hit and memory read from here on to a snapshot file,
+    // which can be served back with ReplayMemory
+    pub fn record_to(&mut self, path: &Path) -> Result<(), MemoryError> {
+        self.recorder = Some(RefCell::new(SnapshotWriter::create(path, &self.osu_path)?));
         Ok(())
     }
 
-    pub fn init(&mut self) -> Result<(), String> {
-        if !self.is_open {
-            return Err(String::from("Cannot init after closing MemoryClient"));
+    fn finish_snapshot_frame(&self) -> Result<(), MemoryError> {
+        match self.recorder {
+            Some(ref recorder) => recorder.borrow_mut().finish_frame(self.watching),
+            None => Ok(())
         }
+    }
 
-        let mut addr = 0;
-        loop {
-            let page = match unsafe { query_page(self.hproc, addr) } {
-                Some(p) => p,
-                None => break
-            };
+    pub fn read_game_file(&self, path: &Path) -> Result<Vec<u8>, MemoryError> {
+        let contents = self.process.read_file(path)?;
+        if let Some(ref recorder) = self.recorder {
+            recorder.borrow_mut().record_file(path, &contents)?;
+        }
+        Ok(contents)
+    }
 
-            let base_address = page.BaseAddress as usize;
-            addr = base_address + page.RegionSize;
+    // the selected map, only parsed again once another map is selected or the file changes
+    pub fn get_beatmap(&mut self, data: &MemoryData) -> Result<Arc<Beatmap>, MemoryError> {
+        let mut path = self.game_path(&data.settings.songs_folder);
+        path.push(&data.menu.folder);
+        path.push(&data.menu.path);
 
-            if page.Protect.0 & PAGE_NOACCESS.0 > 0 || page.Protect.0 & PAGE_GUARD.0 > 0 || page.Protect.0 == 0 {
-                continue;
-            }
+        let modified = self.process.file_modified(&path);
+        if let Some(beatmap) = self.beatmaps.get(&data.menu.md5, modified) {
+            return Ok(beatmap);
+        }
+
+        let contents = self.read_game_file(&path)?;
+        let beatmap = Beatmap::from_bytes(&contents).map_err(|e| MemoryError::InvalidFile {
+            path: path.clone(),
+            message: format!("Failed to parse beatmap file: {}", e)
+        })?;
+        Ok(self.beatmaps.insert(&data.menu.md5, modified, beatmap))
+    }
+
+    // the selected map converted to mode. maps made for taiko, catch or mania can't be
+    // converted and are played as they are in any mode, so they're returned unchanged
+    pub fn get_beatmap_as(&mut self, data: &MemoryData, mode: GameMode) -> Result<Arc<Beatmap>, MemoryError> {
+        let beatmap = self.get_beatmap(data)?;
+        Ok(self.beatmaps.convert(&data.menu.md5, &beatmap, mode).unwrap_or(beatmap))
+    }
+
+    // of the objects passed in the play the last poll read, kept up to date along with the
+    // live pp. none when the play isn't being tracked
+    pub fn get_passed_difficulty(&self, passed: usize) -> Option<DifficultyAttributes> {
+        self.live_pp.as_ref()?.passed_difficulty(passed).cloned()
+    }
+
+    // of a map from get_beatmap or get_beatmap_as
+    pub fn get_difficulty(&mut self, data: &MemoryData, beatmap: &Beatmap, mods: Mods) -> DifficultyAttributes {
+        self.beatmaps.difficulty(&data.menu.md5, beatmap, mods)
+    }
+
+    pub fn addresses(&self) -> &AddressInfo {
+        &self.addresses
+    }
+
+    // for when the addresses are already known, e.g. a synthetic memory layout
+    pub fn set_addresses(&mut self, addresses: AddressInfo) {
+        self.addresses = addresses;
+    }
+
+    // scan regions on this many threads in init, 1 scans them in order and stops early
+    pub fn set_scan_threads(&mut self, threads: usize) {
+        self.scan_threads = threads.max(1);
+    }
+
+    pub fn init(&mut self) -> Result<(), MemoryError> {
+        let regions = self.process.regions()?;
+        self.scan(&regions, Self::all_patterns_found);
 
-            let mut data = vec![0_u8; page.RegionSize];
-            unsafe { read_address(self.hproc, base_address, &mut data, page.RegionSize)? }
+        if !self.all_patterns_found() {
+            return Err(MemoryError::PatternNotFound(
+                self.signatures.patterns.iter()
+                    .filter(|s| self.addresses.get(&s.name) == Some(0))
+                    .map(|s| s.name.clone())
+                    .collect()
+            ));
+        }
+        if self.recorder.is_some() {
+            self.record_pattern_hits(&regions);
+        }
+        self.finish_snapshot_frame()
+    }
+
+    // looks for every pattern without an address yet, stopping early once done says so
+    // when scanning on a single thread
+    fn scan(&mut self, regions: &[MemoryRegion], done: impl Fn(&Self) -> bool) {
+        let patterns: Vec<&[PatternValue]> = self.signatures.patterns.iter().map(|s| s.pattern.as_slice()).collect();
+        let scanner = Scanner::new(&patterns);
+        let mut pending: Vec<bool> = self.signatures.patterns.iter()
+            .map(|s| self.addresses.get(&s.name) == Some(0))
+            .collect();
 
-            if self.search(data.as_slice(), base_address) {
-                return Ok(());
+        if self.scan_threads > 1 {
+            for hit in scanner.scan_parallel(&self.process, regions, &pending, self.scan_threads) {
+                self.set_pattern_address(hit);
+            }
+        } else {
+            let mut buf = Vec::new();
+            for region in regions {
+                for hit in scanner.scan_region(&self.process, region, &mut pending, &mut buf) {
+                    self.set_pattern_address(hit);
+                }
+                if !pending.contains(&true) || done(self) {
+                    break;
+                }
             }
         }
+    }
+
+    // user_info only shows up once it's in use, so init doesn't wait for it outside the
+    // tourney client. returns whether it's been found
+    pub fn find_user_info(&mut self) -> Result<bool, MemoryError> {
+        if self.addresses.user_info != 0 {
+            return Ok(true);
+        }
+        if self.user_info_scanned.is_some_and(|scanned| scanned.elapsed() < USER_INFO_RESCAN) {
+            return Ok(false);
+        }
+        self.user_info_scanned = Some(Instant::now());
+
+        let regions = self.process.regions()?;
+        self.scan(&regions, |client| client.addresses.user_info != 0);
+        if self.addresses.user_info == 0 {
+            return Ok(false);
+        }
+        if self.recorder.is_some() {
+            self.record_pattern_hits(&regions);
+        }
+        Ok(true)
+    }
 
-        Err(String::from("Unable to find all patterns"))
+    fn set_pattern_address(&mut self, hit: PatternHit) {
+        let name = &self.signatures.patterns[hit.pattern].name;
+        if let Some(addr) = self.addresses.get_mut(name) {
+            *addr = hit.address;
+        }
     }
 
-    fn search(&mut self, data: &[u8], base_address: usize) -> bool {
-        let mut valid_count = 0_u8;
-        let mut total_count = 0_u8;
+    fn all_patterns_found(&self) -> bool {
+        self.signatures.patterns.iter().all(|signature| {
+            if self.addresses.get(&signature.name).unwrap_or(0) != 0 {
+                return true;
+            }
 
-        macro_rules! search_for {
-            ( $n:expr; $p:expr ) => {
-                {
-                    total_count += 1;
-                    if $n == 0 {
-                        $n = match_pattern(data, $p, &mut valid_count, base_address);
-                    } else {
-                        valid_count += 1;
-                    }
-                }
+            // user_info is only needed while in the tourney client
+            signature.name == "user_info" && self.addresses.status != 0 && !matches!(
+                unsafe { self.read_u32(self.resolve_chain("status_value")) }.map(OsuStatus::from_u32),
+                Ok(OsuStatus::Tourney)
+            )
+        })
+    }
+
+    // cheap check that the addresses still point at the patterns they were found by
+    fn addresses_valid(&self) -> bool {
+        let patterns_match = self.signatures.patterns.iter().all(|signature| {
+            let addr = self.addresses.get(&signature.name).unwrap_or(0);
+            if addr == 0 {
+                return true;
+            }
+
+            let mut buf = vec![0; signature.pattern.len()];
+            self.process.read(addr, &mut buf).is_ok() && signature.matches(&buf)
+        });
+
+        patterns_match && self.all_patterns_found() && matches!(
+            unsafe { self.read_u32(self.resolve_chain("status_value")) },
+            Ok(status) if !matches!(OsuStatus::from_u32(status), OsuStatus::Other(_))
+        )
+    }
+
+    fn record_pattern_hits(&self, regions: &[MemoryRegion]) {
+        let mut recorder = self.recorder.as_ref().unwrap().borrow_mut();
+        for addr in self.addresses.values() {
+            let region = match regions.iter().find(|r| addr >= r.base && addr < r.base + r.size) {
+                Some(r) => r,
+                None => continue
             };
-        }
 
-        search_for!(self.addresses.status; &[V(0x48), V(0x83), V(0xF8), V(0x04), V(0x73), V(0x1E)]);
-        search_for!(self.addresses.settings_class; &[V(0x83), V(0xE0), V(0x20), V(0x85), V(0xC0), V(0x7E), V(0x2F)]);
-        search_for!(self.addresses.base; &[V(0xF8), V(0x1), V(0x74), V(0x04), V(0x83), V(0x65)]);
-        search_for!(self.addresses.menu_mods; &[V(0xC8), V(0xFF), A(), A(), A(), A(), A(), V(0x81), V(0xD), A(), A(), A(), A(), V(0), V(0x8), V(0), V(0)]);
-        search_for!(self.addresses.play_time; &[V(0x5E), V(0x5F), V(0x5D), V(0xC3), V(0xA1), A(), A(), A(), A(), V(0x89), A(), V(0x4)]);
-        search_for!(self.addresses.chat_checker; &[V(0xA), V(0xD7), V(0x23), V(0x3C), V(0), V(0), A(), V(0x1)]);
-        search_for!(self.addresses.skin_data; &[V(0x75), V(0x21), V(0x8B), V(0x1D)]);
-        search_for!(self.addresses.rulesets; &[V(0x7D), V(0x15), V(0xA1), A(), A(), A(), A(), V(0x85), V(0xC0)]);
-        search_for!(self.addresses.chat_area; &[V(0x33), V(0x47), V(0x9D), V(0xFF), V(0x5B), V(0x7F), V(0xFF), V(0xFF)]);
-        // TODO: likely a more compoact way to do this
-        if self.addresses.status == 0 || (
-            match unsafe { self.read_u32(self.resolve_ptrs(self.addresses.status, &[-4])) } {
-                Ok(status) => status == 22,
-                Err(_e) => false
+            let start = addr.saturating_sub(SNAPSHOT_HIT_WINDOW).max(region.base);
+            let end = (addr + SNAPSHOT_HIT_WINDOW).min(region.base + region.size);
+            let mut window = vec![0; end - start];
+            if self.process.read(start, &mut window).is_ok() {
+                recorder.record(start, &window);
             }
-        ) {
-            search_for!(self.addresses.user_info; &[V(0x52), V(0x30), V(0x8B), V(0xC8), V(0xE8), A(), A(), A(), A(), V(0x8B), V(0xC8), V(0x8D)]);
         }
-        
+    }
+
+    pub fn resolve_chain(&self, name: &str) -> usize {
+        if let Some(addr) = self.chain_cache.borrow().as_ref().and_then(|cache| cache.get(name)) {
+            return *addr;
+        }
 
-        return valid_count == total_count;
+        let addr = match self.signatures.chains.get(name) {
+            Some(chain) => {
+                let base = match self.addresses.get(&chain.base) {
+                    Some(addr) => addr,
+                    None => self.resolve_chain(&chain.base)
+                };
+                self.resolve_ptrs(base, &chain.offsets)
+            },
+            None => 0
+        };
+        if let Some(ref mut cache) = *self.chain_cache.borrow_mut() {
+            cache.insert(name.to_string(), addr);
+        }
+        addr
+    }
+
+    // reads done together as one poll, which resolves each chain at most
+    // once since the pointers won't move in between, and is one snapshot frame
+    fn poll<T>(&mut self, read: impl FnOnce(&Self) -> Result<T, MemoryError>) -> Result<T, MemoryError> {
+        self.process.begin_poll();
+        *self.chain_cache.borrow_mut() = Some(HashMap::new());
+        let data = read(self);
+        *self.chain_cache.borrow_mut() = None;
+        self.finish_snapshot_frame()?;
+        data
     }
 
-    unsafe fn resolve_ptrs(&self, start: usize, offsets: &[isize]) -> usize {
+    // fields of the object a chain points to, which has to be there
+    unsafe fn chain_block(&self, name: &str, fields: Range<usize>) -> Result<MemoryBlock, MemoryError> {
+        match self.resolve_chain(name) {
+            0 => Err(MemoryError::NullPointer(name.into())),
+            addr => self.read_block(addr, fields).map_err(|e| e.context(name))
+        }
+    }
+
+    // a string a chain points to, empty if it doesn't resolve
+    unsafe fn chain_str(&self, name: &str) -> Result<String, MemoryError> {
+        self.read_str(self.resolve_chain(name)).map_err(|e| e.context(name))
+    }
+
+    // mods are stored xor'd with a key next to them
+    unsafe fn read_mods(&self, addr: usize) -> Result<Mods, MemoryError> {
+        let mods = self.read_block(addr, 0x8..0x10)?;
+        Ok(Mods::from_bits((mods.get::<i32>(0xC)? ^ mods.get::<i32>(0x8)?) as u32))
+    }
+
+    pub fn resolve_ptrs(&self, start: usize, offsets: &[isize]) -> usize {
         let mut addr = start;
         for offset in offsets {
             let offset = *offset;
             addr = addr.wrapping_add_signed(offset);
 
-            addr = match self.read_ptr(addr) {
+            addr = match unsafe { self.read_ptr(addr) } {
                 Ok(a) => a,
                 Err(_e) => {
                     addr = 0;
@@ -277,86 +689,331 @@ impl MemoryClient {
         addr
     }
 
-    pub fn get_memory_data(&mut self) -> Result<MemoryData, String> {
+    // a tourney spectator client, which shows a single player's gameplay
+    pub fn get_tourney_client_data(&mut self) -> Result<Option<TourneyClientData>, MemoryError> {
+        self.watched(|client| client.poll(Self::read_tourney_client_data))
+    }
+
+    fn read_tourney_client_data(&self) -> Result<Option<TourneyClientData>, MemoryError> {
+        let gameplay_ruleset1 = self.resolve_chain("gameplay_ruleset1");
+        if gameplay_ruleset1 == 0 {
+            return Ok(None);
+        }
+
+        // the manager's ipc block (TournamentMemoryData::ipc_base_addr) has no known layout for
+        // the slots' scores, so each spectator client's own gameplay is read instead
+        unsafe {
+            Ok(Some(TourneyClientData {
+                player_name: self.chain_str("gameplay_player_name")?,
+                score: self.read_i32(self.resolve_chain("ruleset") + 0x100)?,
+                combo: self.read_i16(gameplay_ruleset1 + 0x94)?,
+                accuracy: self.read_f64(self.resolve_chain("gameplay_accuracy") + 0xC)?,
+                mods: self.read_mods(self.resolve_chain("gameplay_mods"))?
+            }))
+        }
+    }
+
+    fn read_user(&self) -> Result<Option<UserMemoryData>, MemoryError> {
+        if self.addresses.user_info == 0 {
+            return Ok(None);
+        }
+        let profile = self.resolve_chain("user_profile");
+        if profile == 0 {
+            return Ok(None);
+        }
+
+        unsafe {
+            let fields = self.read_block(profile, 0x4..0xA0)?;
+            let user_id = fields.get::<i32>(0x70)?;
+            if user_id <= 0 {
+                return Ok(None);
+            }
+
+            Ok(Some(UserMemoryData {
+                name: self.read_str(fields.get::<u32>(0x30)? as usize)?,
+                user_id,
+                country: country_code(fields.get(0x9C)?),
+                global_rank: fields.get(0x84)?,
+                pp: fields.get::<u16>(0x88)? as i32,
+                accuracy: fields.get(0x4)?,
+                play_count: fields.get(0x7C)?,
+                level: fields.get(0x74)?,
+                play_mode: fields.get(0x80)?
+            }))
+        }
+    }
+
+    fn read_key_overlay(&self) -> Result<Option<KeyOverlayMemoryData>, MemoryError> {
+        let keys = self.resolve_chain("key_overlay");
+        if keys == 0 {
+            return Ok(None);
+        }
+
+        // array of the K1, K2, M1, M2 buttons
+        let read_key = |i: usize| -> Result<KeyState, MemoryError> {
+            unsafe {
+                let key = self.read_u32(keys + 0x8 + 0x4 * i)? as usize;
+                if key == 0 {
+                    return Ok(KeyState::default());
+                }
+                let fields = self.read_block(key, 0x14..0x1D)?;
+                Ok(KeyState {
+                    pressed: fields.get::<i8>(0x1C)? != 0,
+                    count: fields.get(0x14)?
+                })
+            }
+        };
+
+        Ok(Some(KeyOverlayMemoryData {
+            k1: read_key(0)?,
+            k2: read_key(1)?,
+            m1: read_key(2)?,
+            m2: read_key(3)?
+        }))
+    }
+
+    fn read_leaderboard(&self) -> Result<Option<LeaderboardMemoryData>, MemoryError> {
+        if self.resolve_chain("leaderboard") == 0 {
+            return Ok(None);
+        }
+
+        let mut entries = Vec::new();
+        let players = unsafe { self.read_list_ptrs(self.resolve_chain("leaderboard_players"), MAX_LEADERBOARD_ENTRIES)? };
+        for entry in players {
+            if let Some(entry) = self.read_leaderboard_entry(entry)? {
+                entries.push(entry);
+            }
+        }
+
+        Ok(Some(LeaderboardMemoryData {
+            entries,
+            local_player: self.read_leaderboard_entry(self.resolve_chain("leaderboard_local"))?
+        }))
+    }
+
+    fn read_leaderboard_entry(&self, base: usize) -> Result<Option<LeaderboardEntry>, MemoryError> {
+        if base == 0 {
+            return Ok(None);
+        }
+
+        unsafe {
+            let fields = self.read_block(base, 0x8..0x4C)?;
+            let score = fields.get::<u32>(0x20)? as usize;
+            if score == 0 {
+                return Ok(None);
+            }
+            let score_fields = self.read_block(score, 0x1C..0x96)?;
+
+            Ok(Some(LeaderboardEntry {
+                name: self.read_str(fields.get::<u32>(0x8)? as usize)?,
+                score: fields.get(0x30)?,
+                combo: score_fields.get(0x94)?,
+                max_combo: score_fields.get(0x68)?,
+                mods: match score_fields.get::<u32>(0x1C)? as usize {
+                    0 => Mods::NONE,
+                    mods => self.read_mods(mods)?
+                },
+                position: fields.get(0x2C)?,
+                is_passing: fields.get::<i8>(0x4B)? != 0
+            }))
+        }
+    }
+
+    pub fn game_path(&self, path: &str) -> PathBuf {
+        native_path(&self.osu_path, path)
+    }
+
+    // read separately from get_memory_data since most polls don't need the chat
+    pub fn get_chat(&mut self) -> Result<ChatMemoryData, MemoryError> {
+        self.watched(|client| client.poll(Self::read_chat))
+    }
+
+    fn read_chat(&self) -> Result<ChatMemoryData, MemoryError> {
+        let mut channels = Vec::new();
+        unsafe {
+            for channel in self.read_list_ptrs(self.resolve_chain("chat_channels"), MAX_CHAT_CHANNELS)? {
+                if channel == 0 {
+                    continue;
+                }
+
+                let history = self.read_list_ptrs_tail(self.read_u32(channel + 0x10)? as usize, MAX_CHAT_MESSAGES)?;
+                let mut messages = Vec::new();
+                for message in history {
+                    if message == 0 {
+                        continue;
+                    }
+                    messages.push(ChatMessage::new(
+                        &self.read_str(self.read_u32(message + 0x8)? as usize)?,
+                        self.read_str(self.read_u32(message + 0x4)? as usize)?
+                    ));
+                }
+
+                channels.push(ChatChannel {
+                    name: self.read_str(self.read_u32(channel + 0x4)? as usize)?,
+                    messages
+                });
+            }
+        }
+        Ok(ChatMemoryData { channels })
+    }
+
+    pub fn get_memory_data(&mut self) -> Result<MemoryData, MemoryError> {
+        let mut data = self.poll(Self::read_memory_data);
+        if let Ok(ref mut data) = data {
+            self.track_play(data);
+        }
+        data
+    }
+
+    // get_memory_data for the background watcher
+    pub fn watch_memory_data(&mut self) -> Result<MemoryData, MemoryError> {
+        self.watched(Self::get_memory_data)
+    }
+
+    // polls made in the background, which snapshots tell apart from those of commands so
+    // a replay only steps through the commands' ones
+    fn watched<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, MemoryError>) -> Result<T, MemoryError> {
+        self.watching = true;
+        let data = read(self);
+        self.watching = false;
+        data
+    }
+
+    // fills in what comes from earlier polls rather than the current one
+    fn track_play(&mut self, data: &mut MemoryData) {
+        let live = match data.gameplay {
+            Some(ref gameplay) => self.update_live_pp(data, &gameplay.stats),
+            None => None
+        };
+
+        if let Some(ref mut gameplay) = data.gameplay {
+            gameplay.pp = live.map(|(pp, _)| pp);
+            gameplay.passed_combo = live.map(|(_, combo)| combo);
+            if let Some(ref key_overlay) = gameplay.key_overlay {
+                gameplay.kps = self.kps.update(Instant::now(), key_overlay.total());
+                gameplay.peak_kps = self.kps.peak();
+            }
+
+            // a new play or retry starts with no hit errors, which clears the last play's
+            self.hit_error_stats = HitErrorStats::from_errors(&gameplay.hit_errors, gameplay.stats.mods.clock_rate());
+        }
+        data.hit_error_stats = self.hit_error_stats;
+    }
+
+    // starts over on another map or once play_time goes back, as it does on a retry or a
+    // new play, otherwise carries on from the last poll. pp and passed combo
+    fn update_live_pp(&mut self, data: &MemoryData, stats: &ResultsMemoryData) -> Option<(f64, u32)> {
+        let passed = stats.passed_objects();
+        let mode = mode::from_id(stats.mode);
+        let tracked = self.live_pp.as_ref().is_some_and(|live| live.is_for(&data.menu.md5, mode, stats.mods, data.play_time));
+        if !tracked {
+            self.live_pp = None;
+            if let Some((ref md5, failed_mode, mods, ref mut play_time)) = self.live_pp_failed {
+                if *md5 == data.menu.md5 && failed_mode == mode && mods == stats.mods && data.play_time >= *play_time {
+                    *play_time = data.play_time;
+                    return None;
+                }
+            }
+
+            match self.get_beatmap_as(data, mode) {
+                Ok(beatmap) => {
+                    self.live_pp_failed = None;
+                    self.live_pp = Some(LivePp::new(&data.menu.md5, mode, stats.mods, &beatmap));
+                },
+                Err(_) => {
+                    self.live_pp_failed = Some((data.menu.md5.clone(), mode, stats.mods, data.play_time));
+                    return None;
+                }
+            }
+        }
+        let live = self.live_pp.as_mut()?;
+        live.update(stats.score_state(), passed, data.play_time);
+        Some((live.pp(), live.passed_combo()))
+    }
+
+    fn read_memory_data(&self) -> Result<MemoryData, MemoryError> {
         unsafe {
-            let ruleset = self.resolve_ptrs(self.addresses.rulesets, &[-0xB, 0x4]);
-            let settings = self.resolve_ptrs(self.addresses.settings_class, &[0x8]);
-            let menu_base = self.resolve_ptrs(self.addresses.base, &[-0x33]);
-            let beatmap = self.resolve_ptrs(self.addresses.base, &[-0xC]);
-            let menu_beatmap = self.resolve_ptrs(beatmap, &[0]);
+            let ruleset = self.resolve_chain("ruleset");
+            let menu_base = self.chain_block("menu_base", 0x0..0x10)?;
+            let menu_beatmap = self.chain_block("menu_beatmap", 0x2C..0x130)?;
 
-            let status = self.read_u32(self.resolve_ptrs(self.addresses.status, &[-4]))?;
+            let status = OsuStatus::from_u32(self.read_u32(self.resolve_chain("status_value"))?);
 
             let mut tournament = None;
             let mut results = None;
             let mut gameplay = None;
             match status {
-                // TODO: status enums
-                2 => {
-                    let gameplay_ruleset_base = self.resolve_ptrs(ruleset, &[0x68]);
-                    let gameplay_ruleset1 = self.resolve_ptrs(gameplay_ruleset_base, &[0x38]);
-                    let gameplay_ruleset2 = self.resolve_ptrs(gameplay_ruleset_base, &[0x40]);
-                    let gameplay_mods = self.resolve_ptrs(gameplay_ruleset1, &[0x1C]);
+                OsuStatus::Playing => {
+                    let gameplay_ruleset1 = self.chain_block("gameplay_ruleset1", 0x64..0x96)?;
+                    let gameplay_ruleset2 = self.chain_block("gameplay_ruleset2", 0x14..0x24)?;
                     gameplay = Some(
                         GameplayMemoryData {
                             stats: ResultsMemoryData {
-                                player_name: self.read_str(self.resolve_ptrs(gameplay_ruleset1, &[0x28]))?,
-                                mods: (self.read_i32(gameplay_mods + 0xC)? ^ self.read_i32(gameplay_mods + 0x8)?) as u32,
-                                mode: self.read_i32(gameplay_ruleset1 + 0x64)?,
-                                max_combo: self.read_i16(gameplay_ruleset1 + 0x68)?,
+                                player_name: self.chain_str("gameplay_player_name")?,
+                                mods: self.read_mods(self.resolve_chain("gameplay_mods"))?,
+                                mode: gameplay_ruleset1.get(0x64)?,
+                                max_combo: gameplay_ruleset1.get(0x68)?,
                                 score: self.read_i32(ruleset + 0x100)?,
-                                hit100: self.read_i16(gameplay_ruleset1 + 0x88)?,
-                                hit300: self.read_i16(gameplay_ruleset1 + 0x8A)?,
-                                hit50: self.read_i16(gameplay_ruleset1 + 0x8C)?,
-                                hit_geki: self.read_i16(gameplay_ruleset1 + 0x8E)?,
-                                hit_katu: self.read_i16(gameplay_ruleset1 + 0x90)?,
-                                misses: self.read_i16(gameplay_ruleset1 + 0x92)?,
-                                accuracy: self.read_f64(self.resolve_ptrs(ruleset, &[0x68, 0x48])+0xC)?
+                                hit100: gameplay_ruleset1.get(0x88)?,
+                                hit300: gameplay_ruleset1.get(0x8A)?,
+                                hit50: gameplay_ruleset1.get(0x8C)?,
+                                hit_geki: gameplay_ruleset1.get(0x8E)?,
+                                hit_katu: gameplay_ruleset1.get(0x90)?,
+                                misses: gameplay_ruleset1.get(0x92)?,
+                                accuracy: self.read_f64(self.resolve_chain("gameplay_accuracy")+0xC)?
                             },
-                            retries: self.read_i32(menu_base + 0x8)?,
-                            hit_errors: self.read_array::<i32>(self.resolve_ptrs(gameplay_ruleset1, &[0x38, 0x4]))?,
-                            combo: self.read_i16(gameplay_ruleset1 + 0x94)?,
-                            hp_smooth: self.read_f64(gameplay_ruleset2 + 0x14)?,
-                            hp: self.read_f64(gameplay_ruleset2 + 0x1C)?
+                            retries: menu_base.get(0x8)?,
+                            hit_errors: self.read_list::<i32>(self.resolve_chain("gameplay_hit_errors"), MAX_HIT_ERRORS)
+                                .map_err(|e| e.context("gameplay_hit_errors"))?,
+                            combo: gameplay_ruleset1.get(0x94)?,
+                            hp_smooth: gameplay_ruleset2.get(0x14)?,
+                            hp: gameplay_ruleset2.get(0x1C)?,
+                            // the leaderboard is rebuilt while it animates, a torn read just skips it this poll
+                            leaderboard: self.read_leaderboard().ok().flatten(),
+                            // hidden or not created yet in modes without one
+                            key_overlay: self.read_key_overlay().ok().flatten(),
+                            kps: 0.0,
+                            peak_kps: 0.0,
+                            pp: None,
+                            passed_combo: None
                         }
                     )
                 },
-                7 => {
-                    let result_ruleset = self.resolve_ptrs(ruleset, &[0x38]);
-                    let result_mods = self.resolve_ptrs(result_ruleset, &[0x1C]);
+                OsuStatus::ResultsScreen => {
+                    let result_ruleset = self.chain_block("result_ruleset", 0x64..0x94)?;
                     results = Some(
                         ResultsMemoryData {
-                            player_name: self.read_str(self.resolve_ptrs(result_ruleset, &[0x28]))?,
-                            mods: (self.read_i32(result_mods + 0xC)? ^ self.read_i32(result_mods + 0x8)?) as u32,
-                            mode: self.read_i32(result_ruleset + 0x64)?,
-                            max_combo: self.read_i16(result_ruleset + 0x68)?,
-                            score: self.read_i32(result_ruleset + 0x78)?,
-                            hit100: self.read_i16(result_ruleset + 0x88)?,
-                            hit300: self.read_i16(result_ruleset + 0x8A)?,
-                            hit50: self.read_i16(result_ruleset + 0x8C)?,
-                            hit_geki: self.read_i16(result_ruleset + 0x8E)?,
-                            hit_katu: self.read_i16(result_ruleset + 0x90)?,
-                            misses: self.read_i16(result_ruleset + 0x92)?,
-                            accuracy: self.read_f64(self.resolve_ptrs(ruleset, &[0x48])+0xC)?
+                            player_name: self.chain_str("result_player_name")?,
+                            mods: self.read_mods(self.resolve_chain("result_mods"))?,
+                            mode: result_ruleset.get(0x64)?,
+                            max_combo: result_ruleset.get(0x68)?,
+                            score: result_ruleset.get(0x78)?,
+                            hit100: result_ruleset.get(0x88)?,
+                            hit300: result_ruleset.get(0x8A)?,
+                            hit50: result_ruleset.get(0x8C)?,
+                            hit_geki: result_ruleset.get(0x8E)?,
+                            hit_katu: result_ruleset.get(0x90)?,
+                            misses: result_ruleset.get(0x92)?,
+                            accuracy: self.read_f64(self.resolve_chain("result_accuracy")+0xC)?
                         }
                     )
                 },
-                22 => {
-                    let tourney_ruleset1 = self.resolve_ptrs(ruleset, &[0x1C]);
-                    let tourney_ruleset2 = self.resolve_ptrs(ruleset, &[0x20]);
+                OsuStatus::Tourney => {
+                    let tourney_ruleset1 = self.chain_block("tourney_ruleset1", 0x28..0x30)?;
+                    let tourney_ruleset2 = self.chain_block("tourney_ruleset2", 0x28..0x3A)?;
                     tournament = Some(
                         TournamentMemoryData {
                             ipc_state: self.read_i32(ruleset + 0x54)?,
-                            left_stars: self.read_i32(tourney_ruleset1+0x2C)?,
-                            right_stars: self.read_i32(tourney_ruleset2 + 0x2C)?,
-                            bo: self.read_i32(tourney_ruleset2 + 0x30)?,
-                            stars_visible: self.read_i8(tourney_ruleset2 + 0x38)?,
-                            score_visible: self.read_i8(tourney_ruleset2 + 0x39)?,
-                            team_one_name: self.read_str(self.resolve_ptrs(tourney_ruleset1, &[0x20, 0x144]))?,
-                            team_two_name: self.read_str(self.resolve_ptrs(tourney_ruleset2, &[0x20, 0x144]))?,
-                            team_one_score: self.read_i32(tourney_ruleset1 + 0x28)?,
-                            team_two_score: self.read_i32(tourney_ruleset2 + 0x28)?,
-                            ipc_base_addr: self.read_u32(self.resolve_ptrs(ruleset, &[0x34, 0x4])+0x4)?
+                            left_stars: tourney_ruleset1.get(0x2C)?,
+                            right_stars: tourney_ruleset2.get(0x2C)?,
+                            bo: tourney_ruleset2.get(0x30)?,
+                            stars_visible: tourney_ruleset2.get(0x38)?,
+                            score_visible: tourney_ruleset2.get(0x39)?,
+                            team_one_name: self.chain_str("tourney_team_one_name")?,
+                            team_two_name: self.chain_str("tourney_team_two_name")?,
+                            team_one_score: tourney_ruleset1.get(0x28)?,
+                            team_two_score: tourney_ruleset2.get(0x28)?,
+                            ipc_base_addr: self.read_u32(self.resolve_chain("tourney_ipc")+0x4)?
                         }
                     )
                 },
@@ -364,75 +1021,318 @@ impl MemoryClient {
             }
 
             Ok(MemoryData {
-                status: status,
-                chat_status: self.read_i8(self.addresses.chat_checker - 0x20)?,
-                play_time: self.read_i32(self.resolve_ptrs(self.addresses.play_time, &[0x5]))?,
+                status,
+                chat_visible: self.read_i8(self.addresses.chat_checker - 0x20)? != 0,
+                play_time: self.read_i32(self.resolve_chain("play_time_value"))?,
                 settings: SettingsMemoryData {
-                    songs_folder: self.read_str(self.resolve_ptrs(settings, &[0xB8, 0x4]))?,
-                    skin_folder: self.read_str(self.resolve_ptrs(self.addresses.skin_data, &[0x4, 0, 68]))?,
-                    show_interface: self.read_i8(self.resolve_ptrs(settings, &[0x4])+0xC)?
+                    songs_folder: self.chain_str("songs_folder")?,
+                    skin_folder: self.chain_str("skin_folder")?,
+                    show_interface: self.read_i8(self.resolve_chain("settings_interface")+0xC)?
                 },
-                tournament: tournament,
-                results: results,
+                tournament,
+                results,
                 menu: MenuMemoryData {
-                    game_mode: self.read_i32(menu_base)?,
-                    plays: self.read_i32(menu_base + 0xC)?,
-                    artist: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x18]))?,
-                    artist_original: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x1C]))?,
-                    title: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x24]))?,
-                    title_original: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x28]))?,
-                    ar: self.read_f32(menu_beatmap + 0x2C)?,
-                    cs: self.read_f32(menu_beatmap + 0x30)?,
-                    hp: self.read_f32(menu_beatmap + 0x34)?,
-                    od: self.read_f32(menu_beatmap + 0x38)?,
-                    audio_file: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x64]))?,
-                    bg_file: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x68]))?,
-                    folder: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x78]))?,
-                    creator: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x7C]))?,
-                    name: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x80]))?,
-                    path: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x90]))?,
-                    difficulty: self.read_str(self.resolve_ptrs(menu_beatmap, &[0xAC]))?,
-                    beatmap_id: self.read_i32(menu_beatmap + 0xC8)?,
-                    beatmapset_id: self.read_i32(menu_beatmap + 0xCC)?,
-                    ranked_status: self.read_i32(menu_beatmap + 0x12C)?,
-                    md5: self.read_str(self.resolve_ptrs(menu_beatmap, &[0x6C]))?,
-                    object_count: self.read_i32(menu_beatmap + 0xFC)?,
-                    mods: self.read_u32(self.resolve_ptrs(self.addresses.menu_mods, &[0x9]))?
+                    game_mode: menu_base.get(0x0)?,
+                    plays: menu_base.get(0xC)?,
+                    artist: self.chain_str("menu_artist")?,
+                    artist_original: self.chain_str("menu_artist_original")?,
+                    title: self.chain_str("menu_title")?,
+                    title_original: self.chain_str("menu_title_original")?,
+                    ar: menu_beatmap.get(0x2C)?,
+                    cs: menu_beatmap.get(0x30)?,
+                    hp: menu_beatmap.get(0x34)?,
+                    od: menu_beatmap.get(0x38)?,
+                    audio_file: self.chain_str("menu_audio_file")?,
+                    bg_file: self.chain_str("menu_bg_file")?,
+                    folder: self.chain_str("menu_folder")?,
+                    creator: self.chain_str("menu_creator")?,
+                    name: self.chain_str("menu_name")?,
+                    path: self.chain_str("menu_path")?,
+                    difficulty: self.chain_str("menu_difficulty")?,
+                    beatmap_id: menu_beatmap.get(0xC8)?,
+                    beatmapset_id: menu_beatmap.get(0xCC)?,
+                    ranked_status: menu_beatmap.get(0x12C)?,
+                    md5: self.chain_str("menu_md5")?,
+                    object_count: menu_beatmap.get(0xFC)?,
+                    mods: Mods::from_bits(self.read_u32(self.resolve_chain("menu_mods_value"))?)
                 },
-                gameplay: gameplay
+                gameplay,
+                hit_error_stats: None,
+                // the profile is swapped out while osu! logs in or out
+                user: self.read_user().ok().flatten()
             })
         }
     }
 }
 
-impl MemoryReader for MemoryClient {
-    fn handle(&self) -> HANDLE {
-        self.hproc
+impl<P: ProcessMemory> MemoryReader for MemoryClient<P> {
+    fn read_address(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError> {
+        self.process.read(addr, buf)?;
+        if let Some(ref recorder) = self.recorder {
+            recorder.borrow_mut().record(addr, buf);
+        }
+        Ok(())
     }
 }
 
-impl Drop for MemoryClient {
-    fn drop(&mut self) {
-        let _ = self.close();
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::osu_memory_reader::mock::MockMemory;
+    use crate::osu_memory_reader::snapshot::ReplayMemory;
+
+    // osu!'s memory laid out the way the default signatures expect it
+    pub struct Layout {
+        pub memory: MockMemory,
+        pub addresses: AddressInfo
     }
-}
 
-fn match_pattern(data: &[u8], pattern: &[PatternValue], valid_count: &mut u8, base_address: usize) -> usize {
-    let mut pi = 0_usize;
-    for i in 0..data.len() {
-        if !match pattern[pi] {
-            V(v) => data[i] == v,
-            A() => true
-        } {
-            pi = 0;
-            continue;
+    impl Layout {
+        fn new() -> Self {
+            Layout { memory: MockMemory::new(), addresses: AddressInfo::default() }
+        }
+
+        // where a pattern was found, with room on both sides for the chains' offsets
+        pub fn pattern(&mut self, name: &str) -> usize {
+            let addr = self.memory.alloc(0x80) + 0x40;
+            *self.addresses.get_mut(name).unwrap() = addr;
+            addr
+        }
+
+        // a new object of size bytes with a pointer to it at addr
+        pub fn object_at(&mut self, addr: usize, size: usize) -> usize {
+            let object = self.memory.alloc(size);
+            self.memory.write_u32(addr, object as u32);
+            object
+        }
+
+        pub fn str_at(&mut self, addr: usize, text: &str) {
+            let string = self.object_at(addr, 8 + text.len() * 2);
+            self.memory.write_str(string, text);
+        }
+
+        // the pointer at addr
+        pub fn pointer(&self, addr: usize) -> usize {
+            let mut buf = [0; 4];
+            self.memory.read(addr, &mut buf).unwrap();
+            u32::from_le_bytes(buf) as usize
+        }
+
+        // the object the ruleset chain leads to
+        pub fn ruleset(&mut self) -> usize {
+            let rulesets = self.pattern("rulesets");
+            let holder = self.object_at(rulesets - 0xB, 0x8);
+            self.object_at(holder + 0x4, 0x120)
+        }
+
+        pub fn set_status(&mut self, status: u32) {
+            let status_value = self.pointer(self.addresses.status - 0x4);
+            self.memory.write_u32(status_value, status);
         }
 
-        pi += 1;
-        if pi == pattern.len() {
-            *valid_count += 1;
-            return base_address+i+1-pattern.len();
+        pub fn client(self, osu_path: &str) -> MemoryClient<MockMemory> {
+            let mut client = MemoryClient::new(self.memory, PathBuf::from(osu_path));
+            client.set_addresses(self.addresses);
+            client
         }
     }
-    return 0;
+
+    // song select with a map picked, the least there is to read
+    pub fn song_select() -> Layout {
+        let mut layout = Layout::new();
+
+        let status = layout.pattern("status");
+        let status_value = layout.object_at(status - 0x4, 4);
+        layout.memory.write_u32(status_value, 5);
+        let play_time = layout.pattern("play_time");
+        let play_time_value = layout.object_at(play_time + 0x5, 4);
+        layout.memory.write_i32(play_time_value, 12345);
+        let menu_mods = layout.pattern("menu_mods");
+        let menu_mods_value = layout.object_at(menu_mods + 0x9, 4);
+        layout.memory.write_u32(menu_mods_value, (Mods::HIDDEN | Mods::DOUBLE_TIME).bits());
+        let chat_checker = layout.pattern("chat_checker");
+        layout.memory.write_i8(chat_checker - 0x20, 1);
+
+        let settings_class = layout.pattern("settings_class");
+        let settings = layout.object_at(settings_class + 0x8, 0xC0);
+        let interface = layout.object_at(settings + 0x4, 0x10);
+        layout.memory.write_i8(interface + 0xC, 1);
+        let songs_folder = layout.object_at(settings + 0xB8, 0x8);
+        layout.str_at(songs_folder + 0x4, "Songs");
+
+        let base = layout.pattern("base");
+        let menu_base = layout.object_at(base - 0x33, 0x10);
+        layout.memory.write_i32(menu_base, 1).write_i32(menu_base + 0xC, 42);
+        let beatmap = layout.object_at(base - 0xC, 0x4);
+        let menu_beatmap = layout.object_at(beatmap, 0x130);
+        layout.memory.write_f32(menu_beatmap + 0x2C, 9.3)
+            .write_f32(menu_beatmap + 0x30, 4.0)
+            .write_i32(menu_beatmap + 0xC8, 129891)
+            .write_i32(menu_beatmap + 0xCC, 39804)
+            .write_i32(menu_beatmap + 0xFC, 1983)
+            .write_i32(menu_beatmap + 0x12C, 4);
+        layout.str_at(menu_beatmap + 0x18, "xi");
+        layout.str_at(menu_beatmap + 0x24, "FREEDOM DiVE");
+        layout.str_at(menu_beatmap + 0x6C, "da8aae79c8f3306b5d65ec951874a7fb");
+        layout.str_at(menu_beatmap + 0xAC, "FOUR DIMENSIONS");
+
+        // everything else is left unresolved
+        layout.pattern("rulesets");
+        layout
+    }
+
+    #[test]
+    fn resolves_pointer_chains() {
+        let mut layout = Layout::new();
+        let start = layout.memory.alloc(0x20) + 0x10;
+        let first = layout.object_at(start - 0x8, 0x10);
+        let second = layout.object_at(first + 0x4, 0x10);
+        let client = layout.client("");
+
+        assert_eq!(client.resolve_ptrs(start, &[]), start);
+        assert_eq!(client.resolve_ptrs(start, &[-0x8]), first);
+        assert_eq!(client.resolve_ptrs(start, &[-0x8, 0x4]), second);
+        // a null pointer partway, and one pointing nowhere
+        assert_eq!(client.resolve_ptrs(start, &[-0x8, 0x4, 0x0]), 0);
+        assert_eq!(client.resolve_ptrs(0x10, &[0x0]), 0);
+    }
+
+    #[test]
+    fn resolves_named_chains() {
+        let layout = song_select();
+        let base = layout.addresses.base;
+        let client = layout.client("");
+
+        let beatmap = unsafe { client.read_u32(base - 0xC) }.unwrap() as usize;
+        assert_eq!(client.resolve_chain("beatmap"), beatmap);
+        assert_eq!(client.resolve_chain("ruleset"), 0);
+        assert_eq!(client.resolve_chain("not_a_chain"), 0);
+    }
+
+    #[test]
+    fn decodes_memory_data() {
+        let mut client = song_select().client("");
+        let data = client.get_memory_data().unwrap();
+
+        assert_eq!(data.status, OsuStatus::SongSelect);
+        assert_eq!(data.play_time, 12345);
+        assert!(data.chat_visible);
+        assert_eq!(data.settings.songs_folder, "Songs");
+        assert_eq!(data.settings.skin_folder, "");
+        assert_eq!(data.settings.show_interface, 1);
+
+        assert_eq!(data.menu.game_mode, 1);
+        assert_eq!(data.menu.plays, 42);
+        assert_eq!(data.menu.artist, "xi");
+        assert_eq!(data.menu.title, "FREEDOM DiVE");
+        assert_eq!(data.menu.difficulty, "FOUR DIMENSIONS");
+        assert_eq!(data.menu.md5, "da8aae79c8f3306b5d65ec951874a7fb");
+        assert_eq!(data.menu.ar, 9.3);
+        assert_eq!(data.menu.cs, 4.0);
+        assert_eq!(data.menu.beatmap_id, 129891);
+        assert_eq!(data.menu.beatmapset_id, 39804);
+        assert_eq!(data.menu.object_count, 1983);
+        assert_eq!(data.menu.status_name(), "ranked");
+        assert_eq!(data.menu.mods, Mods::HIDDEN | Mods::DOUBLE_TIME);
+
+        assert_eq!(data.current_mode(), GameMode::Taiko);
+        assert!(data.gameplay.is_none() && data.results.is_none() && data.tournament.is_none());
+        assert!(data.user.is_none());
+    }
+
+    #[test]
+    fn fails_without_the_menu_beatmap() {
+        let mut layout = song_select();
+        let base = layout.addresses.base;
+        layout.memory.write_u32(base - 0xC, 0);
+
+        let mut client = layout.client("");
+        assert!(matches!(client.get_memory_data(), Err(e) if e.is_transient()));
+    }
+
+    #[test]
+    fn replays_commands_past_background_polls() {
+        let layout = song_select();
+        let addresses = layout.addresses.clone();
+        let path = std::env::temp_dir().join(format!("osu_twitch_bot-{}-background.osusnap", std::process::id()));
+
+        let mut client = layout.client("");
+        let play_time = unsafe { client.read_u32(addresses.play_time + 0x5) }.unwrap() as usize;
+        client.record_to(&path).unwrap();
+        // the frame init would have written
+        client.finish_snapshot_frame().unwrap();
+        assert_eq!(client.get_memory_data().unwrap().play_time, 12345);
+        client.get_chat().unwrap();
+        client.process.write_i32(play_time, 20000);
+        assert!(client.get_tourney_client_data().unwrap().is_none());
+        client.watch_memory_data().unwrap();
+        client.get_chat().unwrap();
+        assert_eq!(client.get_memory_data().unwrap().play_time, 20000);
+        drop(client);
+
+        let replay = ReplayMemory::open(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(replay.frame_count(), 3);
+        let mut client = MemoryClient::new(replay, PathBuf::new());
+        client.set_addresses(addresses);
+        assert_eq!(client.get_memory_data().unwrap().play_time, 12345);
+        assert_eq!(client.get_memory_data().unwrap().play_time, 20000);
+        assert_eq!(client.process.remaining_frames(), 0);
+    }
+
+    #[test]
+    fn remembers_a_map_that_failed_to_load() {
+        let osu_path = std::env::temp_dir().join(format!("osu_twitch_bot-{}-live-pp", std::process::id()));
+        let map_path = osu_path.join("Songs").join("map").join("map.osu");
+        std::fs::create_dir_all(map_path.parent().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&map_path);
+
+        let mut client = MemoryClient::new(MockMemory::new(), osu_path.clone());
+        let mut data = MemoryData {
+            status: OsuStatus::Playing,
+            chat_visible: false,
+            play_time: 1000,
+            settings: SettingsMemoryData { songs_folder: String::from("Songs"), ..SettingsMemoryData::default() },
+            tournament: None,
+            results: None,
+            menu: MenuMemoryData {
+                md5: String::from("map"),
+                folder: String::from("map"),
+                path: String::from("map.osu"),
+                ..MenuMemoryData::default()
+            },
+            gameplay: None,
+            hit_error_stats: None,
+            user: None
+        };
+        let stats = ResultsMemoryData { hit300: 2, ..ResultsMemoryData::default() };
+        assert!(client.update_live_pp(&data, &stats).is_none());
+
+        // not looked at again for the rest of the play, even once it's there
+        std::fs::write(&map_path, "osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+OverallDifficulty:8
+ApproachRate:9
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1500,1,0,0:0:0:0:
+320,192,2000,1,0,0:0:0:0:
+").unwrap();
+        data.play_time = 2000;
+        assert!(client.update_live_pp(&data, &stats).is_none());
+
+        // but tried again on a retry
+        data.play_time = 1500;
+        let live = client.update_live_pp(&data, &stats);
+        let _ = std::fs::remove_dir_all(&osu_path);
+        assert_eq!(live.map(|(_, combo)| combo), Some(2));
+    }
 }
\ No newline at end of file
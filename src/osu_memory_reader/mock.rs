@@ -0,0 +1,157 @@
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::process::{ MemoryRegion, ProcessMemory };
+
+use std::collections::BTreeMap;
+use paste::paste;
+
+macro_rules! primitive_write_fn {
+    ($t:ident) => {
+        paste! {
+            pub fn [<write_ $t>](&mut self, addr: usize, value: $t) -> &mut Self {
+                self.write(addr, &value.to_le_bytes())
+            }
+        }
+    };
+}
+
+// process memory backed by a map of region base address -> bytes,
+// used to lay out synthetic osu! memory without a running game
+#[derive(Debug, Default, Clone)]
+pub struct MockMemory {
+    regions: BTreeMap<usize, Vec<u8>>,
+    next_alloc: usize
+}
+
+impl MockMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_regions(regions: BTreeMap<usize, Vec<u8>>) -> Self {
+        let mut memory = Self::new();
+        for (base, bytes) in regions {
+            memory.write(base, &bytes);
+        }
+        memory
+    }
+
+    pub fn raw_regions(&self) -> &BTreeMap<usize, Vec<u8>> {
+        &self.regions
+    }
+
+    // writes bytes at addr, growing or merging regions as needed
+    pub fn write(&mut self, addr: usize, bytes: &[u8]) -> &mut Self {
+        let end = addr + bytes.len();
+
+        let base = match self.regions.range(..=addr).next_back() {
+            Some((&base, data)) if base + data.len() >= addr => base,
+            _ => addr
+        };
+        let mut data = self.regions.remove(&base).unwrap_or_default();
+
+        // absorb any regions the write now overlaps or touches
+        let overlapped: Vec<usize> = self.regions.range(base..=end).map(|(&b, _)| b).collect();
+        for b in overlapped {
+            let other = self.regions.remove(&b).unwrap();
+            let offset = b - base;
+            if data.len() < offset + other.len() {
+                data.resize(offset + other.len(), 0);
+            }
+            data[offset..offset + other.len()].copy_from_slice(&other);
+        }
+
+        let offset = addr - base;
+        if data.len() < offset + bytes.len() {
+            data.resize(offset + bytes.len(), 0);
+        }
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+
+        self.regions.insert(base, data);
+        self
+    }
+
+    primitive_write_fn!(u32);
+    primitive_write_fn!(i8);
+    primitive_write_fn!(i16);
+    primitive_write_fn!(i32);
+    primitive_write_fn!(f32);
+    primitive_write_fn!(f64);
+
+    // .NET array layout: type pointer, length, then the elements
+    pub fn write_array(&mut self, addr: usize, elem_size: usize, elems: &[u8]) -> &mut Self {
+        self.write_u32(addr + 4, (elems.len() / elem_size) as u32);
+        self.write(addr + 8, elems)
+    }
+
+    pub fn write_str(&mut self, addr: usize, s: &str) -> &mut Self {
+        let chars: Vec<u8> = s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        self.write_array(addr, 2, &chars)
+    }
+
+    // reserves a zeroed block at an address not used by any other allocation
+    pub fn alloc(&mut self, size: usize) -> usize {
+        if self.next_alloc == 0 {
+            self.next_alloc = self.regions.iter()
+                .map(|(base, data)| base + data.len())
+                .max()
+                .unwrap_or(0)
+                .max(0x1000);
+        }
+
+        let addr = (self.next_alloc + 0xF) & !0xF;
+        self.next_alloc = addr + size.max(1);
+        self.write(addr, &vec![0; size]);
+        addr
+    }
+}
+
+impl ProcessMemory for MockMemory {
+    fn regions(&self) -> Result<Vec<MemoryRegion>, MemoryError> {
+        Ok(self.regions.iter().map(|(&base, data)| MemoryRegion { base, size: data.len() }).collect())
+    }
+
+    fn read(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError> {
+        match self.regions.range(..=addr).next_back() {
+            Some((&base, data)) if addr + buf.len() <= base + data.len() => {
+                buf.copy_from_slice(&data[addr - base..addr - base + buf.len()]);
+                Ok(())
+            },
+            _ => Err(MemoryError::ReadFailed { addr, message: String::from("not mapped") })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_touching_writes() {
+        let mut memory = MockMemory::new();
+        memory.write(0x1000, &[1, 2]).write(0x1004, &[5]).write(0x1002, &[3, 4]);
+        memory.write(0x2000, &[9]);
+
+        assert_eq!(memory.raw_regions().len(), 2);
+        let mut buf = [0; 5];
+        memory.read(0x1000, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4, 5]);
+        // reads can't run past a region
+        assert!(memory.read(0x1003, &mut buf).is_err());
+        assert!(memory.read(0x1800, &mut buf[..1]).is_err());
+    }
+
+    #[test]
+    fn allocates_apart() {
+        let mut memory = MockMemory::new();
+        memory.write(0x4000, &[0xFF; 0x10]);
+        let a = memory.alloc(0x10);
+        let b = memory.alloc(0x3);
+        let c = memory.alloc(0x10);
+
+        assert!(a >= 0x4010 && a.is_multiple_of(0x10));
+        assert!(b >= a + 0x10 && c >= b + 0x3);
+        let mut buf = [0xFF; 0x10];
+        memory.read(a, &mut buf).unwrap();
+        assert_eq!(buf, [0; 0x10]);
+    }
+}
\ No newline at end of file
@@ -1,3 +1,30 @@
+pub mod beatmaps;
+pub mod cache;
+pub mod chat;
+pub mod country;
+pub mod error;
+pub mod hits;
+pub mod keys;
 pub mod mem;
+#[cfg(feature = "mock")]
+pub mod mock;
+// still backs snapshot replays without the feature
+#[cfg(not(feature = "mock"))]
+#[allow(dead_code)]
+mod mock;
+pub mod mode;
+pub mod mods;
+pub mod pp;
+pub mod process;
+pub mod progress;
 mod read;
-mod win;
\ No newline at end of file
+pub mod scan;
+pub mod signatures;
+pub mod snapshot;
+pub mod status;
+pub mod tourney;
+pub mod watcher;
+#[cfg(target_os = "windows")]
+mod win;
+#[cfg(target_os = "linux")]
+mod linux;
\ No newline at end of file
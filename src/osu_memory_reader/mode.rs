@@ -0,0 +1,31 @@
+use rosu_pp::model::mode::GameMode;
+
+// osu! stores the ruleset as 0 std, 1 taiko, 2 catch, 3 mania
+pub fn from_id(id: i32) -> GameMode {
+    match id {
+        1 => GameMode::Taiko,
+        2 => GameMode::Catch,
+        3 => GameMode::Mania,
+        _ => GameMode::Osu
+    }
+}
+
+pub fn name(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Osu => "std",
+        GameMode::Taiko => "taiko",
+        GameMode::Catch => "catch",
+        GameMode::Mania => "mania"
+    }
+}
+
+// a ruleset's name as people write it in chat, or its number
+pub fn parse(text: &str) -> Result<GameMode, String> {
+    match text.to_lowercase().as_str() {
+        "0" | "osu" | "std" | "standard" => Ok(GameMode::Osu),
+        "1" | "taiko" => Ok(GameMode::Taiko),
+        "2" | "catch" | "ctb" | "fruits" => Ok(GameMode::Catch),
+        "3" | "mania" => Ok(GameMode::Mania),
+        _ => Err(format!("Invalid mode '{}', use std, taiko, catch or mania", text))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,272 @@
+use std::fmt;
+use std::ops::{ BitAnd, BitOr, BitOrAssign, Sub };
+
+// osu!stable mod bits, as found in memory and in the api
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Mods(u32);
+
+macro_rules! define_mods {
+    ($($name:ident = $bit:literal, $abbr:literal;)*) => {
+        impl Mods {
+            $(pub const $name: Mods = Mods(1 << $bit);)*
+
+            // in bit order, which is also the order they're displayed in
+            const ABBREVIATIONS: &'static [(Mods, &'static str)] = &[$((Mods::$name, $abbr)),*];
+        }
+    };
+}
+
+define_mods! {
+    NO_FAIL = 0, "NF";
+    EASY = 1, "EZ";
+    TOUCH_DEVICE = 2, "TD";
+    HIDDEN = 3, "HD";
+    HARD_ROCK = 4, "HR";
+    SUDDEN_DEATH = 5, "SD";
+    DOUBLE_TIME = 6, "DT";
+    RELAX = 7, "RX";
+    HALF_TIME = 8, "HT";
+    NIGHTCORE = 9, "NC";
+    FLASHLIGHT = 10, "FL";
+    AUTOPLAY = 11, "AU";
+    SPUN_OUT = 12, "SO";
+    AUTOPILOT = 13, "AP";
+    PERFECT = 14, "PF";
+    KEY4 = 15, "K4";
+    KEY5 = 16, "K5";
+    KEY6 = 17, "K6";
+    KEY7 = 18, "K7";
+    KEY8 = 19, "K8";
+    FADE_IN = 20, "FI";
+    RANDOM = 21, "RA";
+    CINEMA = 22, "CN";
+    TARGET = 23, "TP";
+    KEY9 = 24, "K9";
+    KEY_COOP = 25, "CO";
+    KEY1 = 26, "K1";
+    KEY3 = 27, "K3";
+    KEY2 = 28, "K2";
+    SCORE_V2 = 29, "V2";
+    MIRROR = 30, "MR";
+}
+
+impl Mods {
+    pub const NONE: Mods = Mods(0);
+
+    pub const KEY_MODS: Mods = Mods(
+        Mods::KEY1.0 | Mods::KEY2.0 | Mods::KEY3.0 | Mods::KEY4.0 | Mods::KEY5.0 |
+        Mods::KEY6.0 | Mods::KEY7.0 | Mods::KEY8.0 | Mods::KEY9.0
+    );
+    pub const MANIA_ONLY: Mods = Mods(
+        Mods::KEY_MODS.0 | Mods::KEY_COOP.0 | Mods::FADE_IN.0 | Mods::RANDOM.0 | Mods::MIRROR.0
+    );
+
+    // mods that always come with another one set
+    const IMPLIED: [(Mods, Mods); 2] = [
+        (Mods::NIGHTCORE, Mods::DOUBLE_TIME),
+        (Mods::PERFECT, Mods::SUDDEN_DEATH)
+    ];
+
+    const INCOMPATIBLE: [(Mods, Mods); 10] = [
+        (Mods::EASY, Mods::HARD_ROCK),
+        (Mods::HALF_TIME, Mods::DOUBLE_TIME),
+        // PF before SD so +NFPF isn't reported as NF and SD
+        (Mods::NO_FAIL, Mods::PERFECT),
+        (Mods::NO_FAIL, Mods::SUDDEN_DEATH),
+        (Mods::RELAX, Mods::NO_FAIL),
+        (Mods::RELAX, Mods::PERFECT),
+        (Mods::RELAX, Mods::SUDDEN_DEATH),
+        (Mods::RELAX, Mods::AUTOPILOT),
+        (Mods::AUTOPILOT, Mods::SPUN_OUT),
+        (Mods::AUTOPLAY, Mods::RELAX)
+    ];
+
+    pub fn from_bits(bits: u32) -> Self {
+        Mods(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: Mods) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(&self, other: Mods) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: Mods) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Mods) {
+        self.0 &= !other.0;
+    }
+
+    pub fn with_implied(mut self) -> Self {
+        for (m, implied) in Mods::IMPLIED {
+            if self.contains(m) {
+                self.insert(implied);
+            }
+        }
+        self
+    }
+
+    // how fast the map plays, osu! times everything in the audio's time
+    pub fn clock_rate(&self) -> f64 {
+        if self.intersects(Mods::DOUBLE_TIME | Mods::NIGHTCORE) {
+            1.5
+        } else if self.contains(Mods::HALF_TIME) {
+            0.75
+        } else {
+            1.0
+        }
+    }
+
+    // parses abbreviations like +HDDT, HD,DT or hd dt. NM on its own means no mods
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.strip_prefix('+').unwrap_or(text).to_uppercase();
+        // NM is how to ask for no mods, a lone + is more likely a typo
+        if text.trim().is_empty() {
+            return Err(String::from("No mods after '+'"));
+        }
+
+        let mut mods = Mods::NONE;
+        for part in text.split(|c: char| c == ',' || c == '+' || c.is_whitespace()) {
+            if !part.is_ascii() || !part.len().is_multiple_of(2) {
+                return Err(format!("Invalid mod abbreviation '{}'", part));
+            }
+
+            for i in (0..part.len()).step_by(2) {
+                let abbr = &part[i..i+2];
+                if abbr == "NM" { continue; }
+                match Mods::ABBREVIATIONS.iter().find(|(_, a)| *a == abbr) {
+                    Some((m, _)) => mods.insert(*m),
+                    None => return Err(format!("Invalid mod abbreviation '{}'", abbr))
+                }
+            }
+        }
+
+        Ok(mods.with_implied())
+    }
+
+    // checks the combination can actually be played in the given mode (0 std, 1 taiko, 2 catch, 3 mania)
+    pub fn validate(&self, mode: i32) -> Result<(), String> {
+        for (a, b) in Mods::INCOMPATIBLE {
+            if self.contains(a) && self.contains(b) {
+                return Err(format!("{} and {} can't be used together", a, b));
+            }
+        }
+
+        if (*self & Mods::KEY_MODS).0.count_ones() > 1 {
+            return Err(String::from("Only one key count mod can be used"));
+        }
+        if mode == 3 {
+            if self.intersects(Mods::RELAX | Mods::AUTOPILOT) {
+                return Err(String::from("RX and AP can't be used in mania"));
+            }
+        } else if self.intersects(Mods::MANIA_ONLY) {
+            return Err(format!("{} can only be used in mania", *self & Mods::MANIA_ONLY));
+        }
+        if mode != 0 && self.intersects(Mods::AUTOPILOT | Mods::SPUN_OUT | Mods::TARGET) {
+            return Err(format!("{} can only be used in osu!standard", *self & (Mods::AUTOPILOT | Mods::SPUN_OUT | Mods::TARGET)));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Mods {
+    // abbreviations without a separator, leaving out mods implied by another one (NCDT shows as NC)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hidden = Mods::NONE;
+        for (m, implied) in Mods::IMPLIED {
+            if self.contains(m) {
+                hidden.insert(implied);
+            }
+        }
+
+        for (m, abbr) in Mods::ABBREVIATIONS {
+            if self.contains(*m) && !hidden.contains(*m) {
+                f.write_str(abbr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BitOr for Mods {
+    type Output = Mods;
+
+    fn bitor(self, rhs: Mods) -> Mods {
+        Mods(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Mods {
+    fn bitor_assign(&mut self, rhs: Mods) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Mods {
+    type Output = Mods;
+
+    fn bitand(self, rhs: Mods) -> Mods {
+        Mods(self.0 & rhs.0)
+    }
+}
+
+impl Sub for Mods {
+    type Output = Mods;
+
+    fn sub(self, rhs: Mods) -> Mods {
+        Mods(self.0 & !rhs.0)
+    }
+}
+
+impl From<Mods> for rosu_pp::GameMods {
+    fn from(mods: Mods) -> Self {
+        rosu_pp::GameMods::from(mods.0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        let mods = Mods::parse("+hd,nc").unwrap();
+        assert_eq!(mods, Mods::HIDDEN | Mods::NIGHTCORE | Mods::DOUBLE_TIME);
+        assert_eq!(mods.to_string(), "HDNC");
+        assert_eq!(Mods::parse("+NM").unwrap(), Mods::NONE);
+        assert!(Mods::parse("+XX").is_err());
+        assert_eq!(Mods::parse("+"), Err(String::from("No mods after '+'")));
+    }
+
+    #[test]
+    fn rejects_incompatible_mods() {
+        let error = |text: &str, mode: i32| Mods::parse(text).unwrap().validate(mode).unwrap_err();
+        assert_eq!(error("+EZHR", 0), "EZ and HR can't be used together");
+        assert_eq!(error("+NFPF", 0), "NF and PF can't be used together");
+        assert_eq!(error("+RXNF", 0), "RX and NF can't be used together");
+        assert_eq!(error("+RXPF", 0), "RX and PF can't be used together");
+        assert_eq!(error("+RXSD", 1), "RX and SD can't be used together");
+        assert_eq!(error("+APSO", 0), "AP and SO can't be used together");
+        assert_eq!(error("+K4K7", 3), "Only one key count mod can be used");
+        assert_eq!(error("+RX", 3), "RX and AP can't be used in mania");
+        assert_eq!(error("+FI", 0), "FI can only be used in mania");
+        assert_eq!(error("+SO", 2), "SO can only be used in osu!standard");
+
+        for (text, mode) in [("+HDDTHR", 0), ("+RXHD", 1), ("+APHD", 0), ("+PF", 0), ("+K7FI", 3), ("+SONF", 0)] {
+            assert_eq!(Mods::parse(text).unwrap().validate(mode), Ok(()), "{}", text);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,440 @@
+use crate::osu_memory_reader::mode;
+use crate::osu_memory_reader::mods::Mods;
+
+use rosu_pp::{ Beatmap, Difficulty, GradualPerformance, Performance };
+use rosu_pp::any::{ DifficultyAttributes, ScoreState };
+use rosu_pp::catch::CatchScoreState;
+use rosu_pp::mania::ManiaScoreState;
+use rosu_pp::model::mode::GameMode;
+use rosu_pp::osu::OsuScoreState;
+use rosu_pp::taiko::TaikoScoreState;
+
+// in percent, weighted the way the mode weighs its judgements
+pub fn accuracy(state: &ScoreState, mode: GameMode) -> f64 {
+    let state = state.clone();
+    100.0 * match mode {
+        GameMode::Osu => OsuScoreState::from(state).accuracy(),
+        GameMode::Taiko => TaikoScoreState::from(state).accuracy(),
+        GameMode::Catch => CatchScoreState::from(state).accuracy(),
+        GameMode::Mania => ManiaScoreState::from(state).accuracy()
+    }
+}
+
+// pp of the play in progress, only going over the objects passed since the last
+// update instead of the whole map up to this point
+pub struct LivePp {
+    md5: String,
+    mode: GameMode,
+    mods: Mods,
+    gradual: GradualPerformance,
+    // of the last update, osu! going back in time is a retry, a new play or a seek
+    play_time: i32,
+    passed: usize,
+    pp: f64,
+    // max combo of the objects passed so far
+    passed_combo: u32,
+    // see passed_difficulty, none before the first object is passed
+    passed_difficulty: Option<DifficultyAttributes>
+}
+
+impl LivePp {
+    // the beatmap already converted to the mode being played
+    pub fn new(md5: &str, mode: GameMode, mods: Mods, beatmap: &Beatmap) -> Self {
+        LivePp {
+            md5: md5.into(),
+            mode,
+            mods,
+            gradual: Difficulty::new().mods(mods).gradual_performance(beatmap),
+            play_time: i32::MIN,
+            passed: 0,
+            pp: 0.0,
+            passed_combo: 0,
+            passed_difficulty: None
+        }
+    }
+
+    // whether the play at play_time can be the one being tracked
+    pub fn is_for(&self, md5: &str, mode: GameMode, mods: Mods, play_time: i32) -> bool {
+        self.md5 == md5 && self.mode == mode && self.mods == mods && play_time >= self.play_time
+    }
+
+    pub fn update(&mut self, state: ScoreState, passed: usize, play_time: i32) {
+        self.play_time = play_time;
+        if passed > self.passed {
+            if let Some(attrs) = self.gradual.nth(state, passed - self.passed - 1) {
+                self.pp = attrs.pp();
+                self.passed_combo = attrs.max_combo();
+                self.passed_difficulty = Some(attrs.difficulty_attributes());
+            }
+            self.passed = passed;
+        }
+    }
+
+    pub fn pp(&self) -> f64 {
+        self.pp
+    }
+
+    pub fn passed_combo(&self) -> u32 {
+        self.passed_combo
+    }
+
+    // the same as passed_difficulty(beatmap, mods, passed) without going over the map again
+    pub fn passed_difficulty(&self, passed: usize) -> Option<&DifficultyAttributes> {
+        self.passed_difficulty.as_ref().filter(|_| passed == self.passed)
+    }
+}
+
+fn parse_acc_arg(arg: &str) -> Result<f64, String> {
+    match arg.strip_suffix("%").unwrap_or(arg).parse::<f64>() {
+        Ok(value) => if !(0.0..=100.0).contains(&value) {
+            Err(String::from("Acc must be between 0 and 100"))
+        } else {
+            Ok(value)
+        },
+        Err(_) => Err(String::from("Invalid acc format"))
+    }
+}
+
+fn parse_count_arg(count: &str, arg: &str) -> Result<u32, String> {
+    count.parse::<u32>().or(Err(format!("Invalid number in '{}'", arg)))
+}
+
+// what !pp was asked about, anything left out comes from the current play or map
+#[derive(Debug, Default, PartialEq)]
+pub struct PpArgs {
+    pub acc: Option<f64>,
+    pub mods: Option<Mods>,
+    pub mode: Option<GameMode>,
+    pub combo: Option<u32>,
+    pub misses: Option<u32>,
+    pub n300: Option<u32>,
+    pub n100: Option<u32>,
+    pub n50: Option<u32>,
+    // 320s and 200s in mania
+    pub n_geki: Option<u32>,
+    pub n_katu: Option<u32>
+}
+
+impl PpArgs {
+    // tokens in any order: 98.5% (or 98.5), 3x or 3m for misses, 1200c for combo,
+    // 15x100, 2x50, 5x300, 4x320 and 3x200 for judgement counts, +HDDT and mode=taiko
+    pub fn parse<'a>(args: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut parsed = PpArgs::default();
+        // counts only when there's a number, so words fall through to the invalid argument error
+        let counted = |count: &&str| count.starts_with(|c: char| c.is_ascii_digit());
+        for arg in args.filter(|arg| !arg.is_empty()) {
+            let lower = arg.to_lowercase();
+            if arg.starts_with("+") {
+                parsed.mods = Some(Mods::parse(arg)?);
+            } else if let Some(value) = lower.strip_prefix("mode=") {
+                parsed.mode = Some(mode::parse(value)?);
+            } else if let Some(combo) = lower.strip_suffix("c").filter(counted) {
+                parsed.combo = Some(parse_count_arg(combo, arg)?);
+            } else if let Some(misses) = lower.strip_suffix("x").or(lower.strip_suffix("m")).filter(counted) {
+                parsed.misses = Some(parse_count_arg(misses, arg)?);
+            } else if let Some((count, judgement)) = lower.split_once("x").filter(|(count, _)| counted(count)) {
+                let count = Some(parse_count_arg(count, arg)?);
+                match judgement {
+                    "300" => parsed.n300 = count,
+                    "100" => parsed.n100 = count,
+                    "50" => parsed.n50 = count,
+                    "320" => parsed.n_geki = count,
+                    "200" => parsed.n_katu = count,
+                    _ => return Err(format!("Unknown judgement in '{}', use 300, 100, 50, or 320 and 200 in mania", arg))
+                }
+            } else if arg.strip_suffix("%").unwrap_or(arg).parse::<f64>().is_ok() {
+                parsed.acc = Some(parse_acc_arg(arg)?);
+            } else {
+                return Err(format!("Invalid argument '{}', try e.g. 98.5% 3x 1200c 15x100 +HDDT mode=taiko", arg));
+            }
+        }
+        Ok(parsed)
+    }
+
+    // anything that describes a different score than the one on the results screen
+    pub fn has_score(&self) -> bool {
+        self.acc.is_some() || self.has_counts() || self.combo.is_some()
+    }
+
+    pub fn has_counts(&self) -> bool {
+        [self.misses, self.n300, self.n100, self.n50, self.n_geki, self.n_katu].iter().any(Option::is_some)
+    }
+
+    pub fn validate(&self, mode: GameMode) -> Result<(), String> {
+        if mode != GameMode::Mania && (self.n_geki.is_some() || self.n_katu.is_some()) {
+            return Err(String::from("320s and 200s only exist in mania"));
+        }
+        if mode == GameMode::Taiko && self.n50.is_some() {
+            return Err(String::from("Taiko has no 50s"));
+        }
+        Ok(())
+    }
+
+    pub fn apply<'a>(&self, mut performance: Performance<'a>) -> Performance<'a> {
+        macro_rules! set {
+            ($($field:ident),*) => {
+                $(if let Some(value) = self.$field {
+                    performance = performance.$field(value);
+                })*
+            };
+        }
+        set!(n300, n100, n50, n_geki, n_katu, misses, combo);
+        if let Some(acc) = self.acc {
+            performance = performance.accuracy(acc);
+        }
+        performance
+    }
+}
+
+// difficulty of only the first passed objects, for fc_state
+pub fn passed_difficulty(beatmap: &Beatmap, mods: Mods, passed: usize) -> DifficultyAttributes {
+    Difficulty::new().mods(mods).passed_objects(passed as u32).calculate(beatmap)
+}
+
+// the judgements with the misses turned into 300s, passed is the difficulty of the
+// objects passed so far (see passed_difficulty)
+pub fn fc_state(state: &ScoreState, passed: &DifficultyAttributes) -> ScoreState {
+    let mut fc = state.clone();
+    match passed {
+        // a miss in catch is a fruit or a droplet, the fruits not caught are the missed ones
+        DifficultyAttributes::Catch(attrs) => {
+            let fruit_misses = attrs.n_fruits.saturating_sub(fc.n300).min(fc.misses);
+            fc.n300 += fruit_misses;
+            fc.n100 += fc.misses - fruit_misses;
+        },
+        _ => fc.n300 += fc.misses
+    }
+    fc.misses = 0;
+    fc
+}
+
+// the play as a full combo, with the rest of the map played at the accuracy of fc_state
+pub fn if_fc(
+    difficulty: DifficultyAttributes,
+    passed: &DifficultyAttributes,
+    mods: Mods,
+    state: &ScoreState,
+    mode: GameMode
+) -> f64 {
+    let acc = accuracy(&fc_state(state, passed), mode);
+    difficulty.performance().mods(mods.bits()).accuracy(acc).misses(0).calculate().pp()
+}
+
+// the play if every object left is hit perfectly, carrying on the current combo.
+// passed_combo is the max combo of the objects passed so far, from LivePp
+pub fn if_rest_perfect(
+    difficulty: DifficultyAttributes,
+    mods: Mods,
+    state: &ScoreState,
+    combo: u32,
+    passed_combo: u32,
+    mode: GameMode
+) -> f64 {
+    let remaining_combo = difficulty.max_combo().saturating_sub(passed_combo);
+    // only the judgements worse than the best one are given, rosu-pp fills in the rest
+    let performance = difficulty.performance().mods(mods.bits())
+        .misses(state.misses)
+        .combo(state.max_combo.max(combo + remaining_combo));
+    let performance = match mode {
+        GameMode::Osu => performance.n100(state.n100).n50(state.n50),
+        GameMode::Taiko => performance.n100(state.n100),
+        // katus are the missed tiny droplets
+        GameMode::Catch => performance.n_katu(state.n_katu),
+        GameMode::Mania => performance.n_katu(state.n_katu).n100(state.n100).n50(state.n50)
+    };
+    performance.calculate().pp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4 circles and a slider with a droplet and tiny droplets when converted to catch
+    const MAP: &str = "osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1500,1,0,0:0:0:0:
+320,192,2000,1,0,0:0:0:0:
+448,192,2500,1,0,0:0:0:0:
+64,192,3000,2,0,L|384:192,1,280
+";
+
+    fn beatmap(mode: GameMode) -> Beatmap {
+        let mut beatmap = Beatmap::from_bytes(MAP.as_bytes()).unwrap();
+        assert!(beatmap.convert_in_place(mode).success());
+        beatmap
+    }
+
+    fn parse(text: &str) -> Result<PpArgs, String> {
+        PpArgs::parse(text.split(' '))
+    }
+
+    #[test]
+    fn parses_every_token() {
+        let cases: [(&str, PpArgs); 16] = [
+            ("98.5%", PpArgs { acc: Some(98.5), ..PpArgs::default() }),
+            ("98.5", PpArgs { acc: Some(98.5), ..PpArgs::default() }),
+            ("100", PpArgs { acc: Some(100.0), ..PpArgs::default() }),
+            ("3x", PpArgs { misses: Some(3), ..PpArgs::default() }),
+            ("3M", PpArgs { misses: Some(3), ..PpArgs::default() }),
+            ("1200c", PpArgs { combo: Some(1200), ..PpArgs::default() }),
+            ("5x300", PpArgs { n300: Some(5), ..PpArgs::default() }),
+            ("15x100", PpArgs { n100: Some(15), ..PpArgs::default() }),
+            ("2X50", PpArgs { n50: Some(2), ..PpArgs::default() }),
+            ("4x320", PpArgs { n_geki: Some(4), ..PpArgs::default() }),
+            ("3x200", PpArgs { n_katu: Some(3), ..PpArgs::default() }),
+            ("+HDDT", PpArgs { mods: Some(Mods::HIDDEN | Mods::DOUBLE_TIME), ..PpArgs::default() }),
+            ("+NM", PpArgs { mods: Some(Mods::NONE), ..PpArgs::default() }),
+            ("mode=taiko", PpArgs { mode: Some(GameMode::Taiko), ..PpArgs::default() }),
+            ("MODE=ctb", PpArgs { mode: Some(GameMode::Catch), ..PpArgs::default() }),
+            ("", PpArgs::default())
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(text), Ok(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_tokens_in_any_order() {
+        let args = parse("+HD 1m  1200c 15x100 97%").unwrap();
+        assert_eq!(args, PpArgs {
+            acc: Some(97.0),
+            mods: Some(Mods::HIDDEN),
+            combo: Some(1200),
+            misses: Some(1),
+            n100: Some(15),
+            ..PpArgs::default()
+        });
+        assert!(args.has_score() && args.has_counts());
+
+        let args = parse("+DT mode=mania").unwrap();
+        assert!(!args.has_score() && !args.has_counts());
+        assert!(parse("1200c").unwrap().has_score() && !parse("1200c").unwrap().has_counts());
+    }
+
+    #[test]
+    fn explains_bad_tokens() {
+        let cases = [
+            ("101%", "Acc must be between 0 and 100"),
+            ("-5", "Acc must be between 0 and 100"),
+            ("99999999999c", "Invalid number in '99999999999c'"),
+            ("99999999999x", "Invalid number in '99999999999x'"),
+            ("5x150", "Unknown judgement in '5x150', use 300, 100, 50, or 320 and 200 in mania"),
+            ("1.5x", "Invalid number in '1.5x'"),
+            ("+XX", "Invalid mod abbreviation 'XX'"),
+            ("+", "No mods after '+'"),
+            ("mode=osu!", "Invalid mode 'osu!', use std, taiko, catch or mania"),
+            ("abc", "Invalid argument 'abc', try e.g. 98.5% 3x 1200c 15x100 +HDDT mode=taiko"),
+            ("x100", "Invalid argument 'x100', try e.g. 98.5% 3x 1200c 15x100 +HDDT mode=taiko"),
+            ("98.5%%", "Invalid argument '98.5%%', try e.g. 98.5% 3x 1200c 15x100 +HDDT mode=taiko")
+        ];
+        for (text, error) in cases {
+            assert_eq!(parse(text), Err(String::from(error)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn checks_judgements_against_the_mode() {
+        assert_eq!(parse("4x320").unwrap().validate(GameMode::Osu), Err(String::from("320s and 200s only exist in mania")));
+        assert_eq!(parse("3x200").unwrap().validate(GameMode::Catch), Err(String::from("320s and 200s only exist in mania")));
+        assert_eq!(parse("2x50").unwrap().validate(GameMode::Taiko), Err(String::from("Taiko has no 50s")));
+        assert_eq!(parse("4x320 3x200 2x50").unwrap().validate(GameMode::Mania), Ok(()));
+        assert_eq!(parse("2x50").unwrap().validate(GameMode::Catch), Ok(()));
+    }
+
+    #[test]
+    fn turns_misses_into_300s() {
+        let map = beatmap(GameMode::Osu);
+        let difficulty = Difficulty::new().calculate(&map);
+        let state = ScoreState { max_combo: 3, n300: 3, n100: 1, misses: 2, ..ScoreState::default() };
+        assert_eq!(fc_state(&state, &difficulty), ScoreState { n300: 5, misses: 0, ..state.clone() });
+    }
+
+    #[test]
+    fn splits_catch_misses_between_fruits_and_droplets() {
+        // 6 fruits, the slider's droplet and 14 tiny droplets
+        let map = beatmap(GameMode::Catch);
+        let whole = Difficulty::new().calculate(&map);
+        let state = ScoreState { max_combo: 3, n300: 5, n50: 14, misses: 2, ..ScoreState::default() };
+        assert_eq!(fc_state(&state, &whole), ScoreState { n300: 6, n100: 1, misses: 0, ..state.clone() });
+
+        // up to the droplet, with one fruit and the droplet missed
+        let passed = passed_difficulty(&map, Mods::NONE, 6);
+        let state = ScoreState { max_combo: 3, n300: 4, n50: 7, misses: 2, ..ScoreState::default() };
+        assert_eq!(fc_state(&state, &passed), ScoreState { n300: 5, n100: 1, misses: 0, ..state.clone() });
+
+        // every miss so far was a fruit
+        let state = ScoreState { max_combo: 2, n300: 3, n100: 1, misses: 2, ..ScoreState::default() };
+        assert_eq!(fc_state(&state, &passed), ScoreState { n300: 5, n100: 1, misses: 0, ..state.clone() });
+    }
+
+    #[test]
+    fn calculates_fc_pp() {
+        let map = beatmap(GameMode::Osu);
+        let difficulty = Difficulty::new().calculate(&map);
+        let ss = difficulty.clone().performance().calculate().pp();
+        let perfect = ScoreState { max_combo: 7, n300: 5, ..ScoreState::default() };
+        assert_eq!(if_fc(difficulty.clone(), &difficulty, Mods::NONE, &perfect, GameMode::Osu), ss);
+
+        let missed = ScoreState { max_combo: 3, n300: 4, misses: 1, ..ScoreState::default() };
+        let pp = difficulty.clone().performance().state(missed.clone()).calculate().pp();
+        let fc = if_fc(difficulty.clone(), &difficulty, Mods::NONE, &missed, GameMode::Osu);
+        // the miss turned into a 300 is an SS
+        assert_eq!(fc, ss);
+        assert!(pp < fc);
+
+        let hundred = ScoreState { max_combo: 7, n300: 4, n100: 1, ..ScoreState::default() };
+        let fc = if_fc(difficulty.clone(), &difficulty, Mods::NONE, &hundred, GameMode::Osu);
+        assert!(fc < ss);
+    }
+
+    #[test]
+    fn calculates_rest_perfect_pp() {
+        let map = beatmap(GameMode::Osu);
+        let difficulty = Difficulty::new().calculate(&map);
+        let ss = difficulty.clone().performance().calculate().pp();
+
+        // 3 circles hit, the rest of the map still to play
+        let clean = ScoreState { max_combo: 3, n300: 3, ..ScoreState::default() };
+        assert_eq!(if_rest_perfect(difficulty.clone(), Mods::NONE, &clean, 3, 3, GameMode::Osu), ss);
+
+        // the combo broken on the 3rd circle only carries on from there
+        let missed = ScoreState { max_combo: 2, n300: 2, misses: 1, ..ScoreState::default() };
+        let rest = if_rest_perfect(difficulty.clone(), Mods::NONE, &missed, 0, 3, GameMode::Osu);
+        let expected = difficulty.clone().performance()
+            .state(ScoreState { max_combo: 4, n300: 4, misses: 1, ..ScoreState::default() })
+            .calculate().pp();
+        assert_eq!(rest, expected);
+        assert!(rest < ss);
+    }
+
+    #[test]
+    fn keeps_the_passed_difficulty_of_a_live_play() {
+        let map = beatmap(GameMode::Catch);
+        let mut live = LivePp::new("map", GameMode::Catch, Mods::NONE, &map);
+        assert!(live.passed_difficulty(0).is_none());
+
+        live.update(ScoreState { max_combo: 2, n300: 2, ..ScoreState::default() }, 2, 1000);
+        live.update(ScoreState { max_combo: 3, n300: 4, n50: 7, misses: 2, ..ScoreState::default() }, 6, 2000);
+        let expected = passed_difficulty(&map, Mods::NONE, 6);
+        let passed = live.passed_difficulty(6).unwrap();
+        assert_eq!((passed.stars(), passed.max_combo()), (expected.stars(), expected.max_combo()));
+        // out of date for any other point in the play
+        assert!(live.passed_difficulty(5).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,304 @@
+#[cfg(target_os = "windows")]
+use crate::osu_memory_reader::win::*;
+#[cfg(target_os = "linux")]
+use crate::osu_memory_reader::linux::*;
+
+use crate::osu_memory_reader::error::MemoryError;
+
+use std::fmt;
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+
+#[derive(Debug, Clone)]
+pub struct MemoryRegion {
+    pub base: usize,
+    pub size: usize
+}
+
+// a source of process memory: a live process, or something standing in for one
+pub trait ProcessMemory: Sync {
+    fn regions(&self) -> Result<Vec<MemoryRegion>, MemoryError>;
+    fn read(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError>;
+
+    // called at the start of every MemoryClient::get_memory_data
+    fn begin_poll(&mut self) {}
+
+    // files the game has open, such as the current .osu file
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>, MemoryError> {
+        std::fs::read(path).map_err(|e| MemoryError::io(path, e))
+    }
+
+    // when a file was last changed, none when that can't be told
+    fn file_modified(&self, path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+// what tells one osu! run apart from another, so addresses found in it can be reused safely
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessIdentity {
+    pub proc_id: u32,
+    pub start_time: u64,
+    // a fingerprint of the exe's size and modified time, not of its contents
+    pub exe_stamp: u64
+}
+
+// FNV-1a, only needs to notice a different osu! build
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+// a running process that can be attached to
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub proc_id: u32,
+    pub exe_path: PathBuf,
+    pub start_time: u64
+}
+
+// which of several running osu! processes to attach to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessSelector {
+    // processes of one install, given as its folder or the exe itself
+    Path(PathBuf),
+    Pid(u32),
+    // the most recently started process
+    Newest
+}
+
+impl ProcessSelector {
+    // "newest", a pid, or otherwise a path
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(String::from("Process selector is empty"));
+        }
+        if text.eq_ignore_ascii_case("newest") {
+            return Ok(ProcessSelector::Newest);
+        }
+        match text.parse::<u32>() {
+            Ok(pid) => Ok(ProcessSelector::Pid(pid)),
+            Err(_) => Ok(ProcessSelector::Path(PathBuf::from(text)))
+        }
+    }
+
+    // info is none for a process that exited since being opened, which can't be picked
+    pub fn select<T>(&self, processes: Vec<T>, info: impl Fn(&T) -> Option<ProcessInfo>) -> Vec<T> {
+        let mut processes: Vec<(T, ProcessInfo)> = processes.into_iter()
+            .filter_map(|process| info(&process).map(|info| (process, info)))
+            .collect();
+        match self {
+            ProcessSelector::Path(path) => processes.retain(|(_, info)| path_starts_with(&info.exe_path, path)),
+            ProcessSelector::Pid(pid) => processes.retain(|(_, info)| info.proc_id == *pid),
+            ProcessSelector::Newest => {
+                let newest = processes.iter().map(|(_, info)| info.start_time).max();
+                processes.retain(|(_, info)| Some(info.start_time) == newest);
+                processes.truncate(1);
+            }
+        }
+        processes.into_iter().map(|(process, _)| process).collect()
+    }
+}
+
+// windows doesn't care about case, so a path typed into setup.cfg may not match the
+// one osu! was started from letter for letter
+fn path_starts_with(path: &Path, prefix: &Path) -> bool {
+    let lowercase = |path: &Path| path.components()
+        .map(|component| component.as_os_str().to_string_lossy().to_lowercase())
+        .collect::<Vec<String>>();
+    lowercase(path).starts_with(&lowercase(prefix))
+}
+
+impl fmt::Display for ProcessSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessSelector::Path(path) => write!(f, "path {}", path.display()),
+            ProcessSelector::Pid(pid) => write!(f, "pid {}", pid),
+            ProcessSelector::Newest => write!(f, "newest")
+        }
+    }
+}
+
+pub struct NativeProcess {
+    pub proc_id: u32,
+    handle: ProcessHandle,
+    pub module: ModuleInfo,
+    pub exe_path: PathBuf
+}
+
+impl NativeProcess {
+    pub fn open(proc_name: &str, selector: &ProcessSelector) -> Result<Self, MemoryError> {
+        NativeProcess::open_selected(proc_name, Some(selector))?.into_iter().next()
+            .ok_or(MemoryError::ProcessNotFound(proc_name.into()))
+    }
+
+    // every process that could be attached to, oldest first
+    pub fn list(proc_name: &str) -> Result<Vec<ProcessInfo>, MemoryError> {
+        let mut processes: Vec<ProcessInfo> = NativeProcess::open_all(proc_name)?.iter()
+            .filter_map(|process| process.info().ok())
+            .collect();
+        processes.sort_by_key(|process| process.start_time);
+        Ok(processes)
+    }
+
+    // the processes picked by the selector, or all of them without one
+    pub fn open_selected(proc_name: &str, selector: Option<&ProcessSelector>) -> Result<Vec<Self>, MemoryError> {
+        let processes = NativeProcess::open_all(proc_name)?;
+        let selector = match selector {
+            Some(selector) => selector,
+            None => return Ok(processes)
+        };
+
+        let processes = selector.select(processes, |process| process.info().ok());
+        if processes.is_empty() {
+            return Err(MemoryError::ProcessNotFound(format!("{} with {}", proc_name, selector)));
+        }
+        Ok(processes)
+    }
+
+    // every running process with this name that could be opened, e.g. all the clients of a tourney
+    pub fn open_all(proc_name: &str) -> Result<Vec<Self>, MemoryError> {
+        let processes: Vec<Self> = unsafe { find_procs(proc_name)? }.into_iter()
+            .filter_map(|pid| NativeProcess::open_pid(pid).ok())
+            .collect();
+
+        if processes.is_empty() {
+            return Err(MemoryError::ProcessNotFound(proc_name.into()));
+        }
+        Ok(processes)
+    }
+
+    pub fn open_pid(proc_id: u32) -> Result<Self, MemoryError> {
+        unsafe {
+            let handle = open_process(proc_id)?;
+            let (exe_path, module) = match get_proc_info(handle) {
+                Ok(info) => info,
+                Err(e) => {
+                    let _ = close_handle(handle);
+                    return Err(e);
+                }
+            };
+
+            Ok(NativeProcess {
+                proc_id,
+                handle,
+                module,
+                exe_path: PathBuf::from(exe_path.trim_end_matches('\0'))
+            })
+        }
+    }
+
+    pub fn start_time(&self) -> Result<u64, MemoryError> {
+        unsafe { process_start_time(self.handle) }
+    }
+
+    pub fn info(&self) -> Result<ProcessInfo, MemoryError> {
+        Ok(ProcessInfo {
+            proc_id: self.proc_id,
+            exe_path: self.exe_path.clone(),
+            start_time: self.start_time()?
+        })
+    }
+
+    pub fn is_alive(&self) -> bool {
+        unsafe { process_alive(self.handle) }
+    }
+
+    // reads of an exited process fail like bad addresses do, so tell them apart here
+    fn check_alive<T>(&self, result: Result<T, MemoryError>) -> Result<T, MemoryError> {
+        match result {
+            Err(_) if !self.is_alive() => Err(MemoryError::ProcessGone(self.proc_id)),
+            result => result
+        }
+    }
+
+    // the exe's size and modification time stand in for its contents, an update changes both
+    pub fn identity(&self) -> Result<ProcessIdentity, MemoryError> {
+        let metadata = std::fs::metadata(&self.exe_path).map_err(|e| MemoryError::io(&self.exe_path, e))?;
+        let modified = metadata.modified()
+            .ok()
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+        Ok(ProcessIdentity {
+            proc_id: self.proc_id,
+            start_time: self.start_time()?,
+            exe_stamp: hash_bytes(&[metadata.len().to_le_bytes(), modified.to_le_bytes()].concat())
+        })
+    }
+}
+
+// the handle and module info are plain values that any thread can use, but on
+// windows they hold raw pointers, which would make the process !Send and !Sync
+unsafe impl Send for NativeProcess {}
+unsafe impl Sync for NativeProcess {}
+
+impl ProcessMemory for NativeProcess {
+    fn regions(&self) -> Result<Vec<MemoryRegion>, MemoryError> {
+        self.check_alive(unsafe { readable_regions(self.handle) })
+    }
+
+    fn read(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError> {
+        let size = buf.len();
+        self.check_alive(unsafe { read_address(self.handle, addr, buf, size) })
+    }
+}
+
+impl Drop for NativeProcess {
+    fn drop(&mut self) {
+        let _ = unsafe { close_handle(self.handle) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(proc_id: u32, exe_path: &str, start_time: u64) -> ProcessInfo {
+        ProcessInfo { proc_id, exe_path: PathBuf::from(exe_path), start_time }
+    }
+
+    fn select(selector: &str, processes: &[ProcessInfo]) -> Vec<u32> {
+        ProcessSelector::parse(selector).unwrap()
+            .select(processes.to_vec(), |process| Some(process.clone()))
+            .iter().map(|process| process.proc_id).collect()
+    }
+
+    #[test]
+    fn parses_selectors() {
+        assert_eq!(ProcessSelector::parse("newest"), Ok(ProcessSelector::Newest));
+        assert_eq!(ProcessSelector::parse(" Newest "), Ok(ProcessSelector::Newest));
+        assert_eq!(ProcessSelector::parse("1234"), Ok(ProcessSelector::Pid(1234)));
+        assert_eq!(ProcessSelector::parse("/games/osu!/"), Ok(ProcessSelector::Path(PathBuf::from("/games/osu!/"))));
+        // too big for a pid
+        assert_eq!(ProcessSelector::parse("99999999999"), Ok(ProcessSelector::Path(PathBuf::from("99999999999"))));
+        assert!(ProcessSelector::parse("  ").is_err());
+    }
+
+    #[test]
+    fn selects_processes() {
+        let processes = [
+            process(10, "/games/osu!/osu!.exe", 300),
+            process(20, "/games/osu!/osu!.exe", 500),
+            process(30, "/home/me/.wine/drive_c/osu!/osu!.exe", 400),
+            process(40, "/games/osu! tourney/osu!.exe", 100)
+        ];
+        assert_eq!(select("/games/osu!", &processes), [10, 20]);
+        assert_eq!(select("/games/osu!/osu!.exe", &processes), [10, 20]);
+        assert_eq!(select("/HOME/me/.wine/drive_c/OSU!", &processes), [30]);
+        assert!(select("/games/osu", &processes).is_empty());
+        assert_eq!(select("30", &processes), [30]);
+        assert!(select("50", &processes).is_empty());
+        assert_eq!(select("newest", &processes), [20]);
+        assert!(select("newest", &[]).is_empty());
+    }
+
+    #[test]
+    fn skips_exited_processes() {
+        let processes = [process(10, "/games/osu!/osu!.exe", 300), process(20, "/games/osu!/osu!.exe", 500)];
+        let alive = |process: &ProcessInfo| Some(process.clone()).filter(|process| process.proc_id != 20);
+        let selected = ProcessSelector::Newest.select(processes.to_vec(), alive);
+        assert_eq!(selected.iter().map(|process| process.proc_id).collect::<Vec<u32>>(), [10]);
+        assert!(ProcessSelector::Pid(20).select(processes.to_vec(), alive).is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,172 @@
+use rosu_pp::Beatmap;
+use rosu_pp::model::hit_object::{ HitObject, HitObjectKind };
+
+// where a play is in the map, from osu!'s audio time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapProgress {
+    // 0 to 1, from the first object's start to the last object's end
+    pub percent: f64,
+    // in seconds of real time, so DT and HT plays show what the viewer sees
+    pub elapsed: f64,
+    pub remaining: f64,
+    pub length: f64,
+    pub in_break: bool,
+    pub kiai: bool
+}
+
+impl MapProgress {
+    // none for a map without objects
+    pub fn new(beatmap: &Beatmap, play_time: i32, clock_rate: f64) -> Option<Self> {
+        let first = beatmap.hit_objects.first()?.start_time;
+        // mania holds can end after objects that start later
+        let last = beatmap.hit_objects.iter()
+            .map(|h| end_time(beatmap, h))
+            .fold(first, f64::max);
+
+        let time = play_time as f64;
+        let length = last - first;
+        let elapsed = (time - first).clamp(0.0, length);
+        let kiai = point_at(&beatmap.effect_points, time, |p| p.time).is_some_and(|p| p.kiai);
+
+        Some(MapProgress {
+            percent: if length > 0.0 { elapsed / length } else { 1.0 },
+            elapsed: elapsed / clock_rate / 1000.0,
+            remaining: (length - elapsed) / clock_rate / 1000.0,
+            length: length / clock_rate / 1000.0,
+            in_break: beatmap.breaks.iter().any(|b| b.start_time <= time && time < b.end_time),
+            kiai
+        })
+    }
+}
+
+// the control point in effect at time, none before the first one
+fn point_at<T>(points: &[T], time: f64, point_time: impl Fn(&T) -> f64) -> Option<&T> {
+    let i = points.partition_point(|p| point_time(p) <= time);
+    i.checked_sub(1).map(|i| &points[i])
+}
+
+// rosu-pp only exposes start times, so slider ends are worked out from the
+// slider velocity the same way osu! does
+fn end_time(beatmap: &Beatmap, h: &HitObject) -> f64 {
+    match h.kind {
+        HitObjectKind::Circle => h.start_time,
+        HitObjectKind::Slider(ref slider) => {
+            // objects before the first timing point use it anyway
+            let beat_len = point_at(&beatmap.timing_points, h.start_time, |p| p.time)
+                .or(beatmap.timing_points.first())
+                .map_or(1000.0, |p| p.beat_len);
+            let sv = point_at(&beatmap.difficulty_points, h.start_time, |p| p.time)
+                .map_or(1.0, |p| p.slider_velocity);
+            // osu!pixels per ms
+            let velocity = 100.0 * beatmap.slider_multiplier * sv / beat_len;
+            let dist = slider.expected_dist.unwrap_or(0.0) * slider.span_count() as f64;
+            if velocity > 0.0 { h.start_time + dist / velocity } else { h.start_time }
+        },
+        HitObjectKind::Spinner(ref spinner) => h.start_time + spinner.duration,
+        HitObjectKind::Hold(ref hold) => h.start_time + hold.duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosu_pp::model::beatmap::BreakPeriod;
+    use rosu_pp::model::control_point::{ DifficultyPoint, EffectPoint, TimingPoint };
+    use rosu_pp::model::hit_object::{ HoldNote, Pos, Slider, Spinner };
+
+    fn object(start_time: f64, kind: HitObjectKind) -> HitObject {
+        HitObject { pos: Pos::default(), start_time, kind }
+    }
+
+    fn slider(expected_dist: f64, repeats: usize) -> HitObjectKind {
+        HitObjectKind::Slider(Slider {
+            expected_dist: Some(expected_dist),
+            repeats,
+            control_points: Box::default(),
+            node_sounds: Box::default()
+        })
+    }
+
+    // 120 bpm from 1000 with 1.4 slider multiplier, so sliders move 0.28 osu!pixels per ms
+    // until the 2x sv at 3000. kiai from 2000 to 4000 and a break from 5000 to 8000
+    fn beatmap() -> Beatmap {
+        Beatmap {
+            slider_multiplier: 1.4,
+            timing_points: vec![TimingPoint::new(1000.0, 500.0)],
+            difficulty_points: vec![DifficultyPoint::new(3000.0, -50.0, 2.0)],
+            effect_points: vec![EffectPoint::new(2000.0, true), EffectPoint::new(4000.0, false)],
+            breaks: vec![BreakPeriod { start_time: 5000.0, end_time: 8000.0 }],
+            hit_objects: vec![
+                object(500.0, slider(140.0, 0)),
+                object(1000.0, HitObjectKind::Circle),
+                object(2000.0, slider(140.0, 1)),
+                object(3500.0, slider(140.0, 0)),
+                object(4000.0, HitObjectKind::Spinner(Spinner { duration: 1000.0 })),
+                object(8000.0, HitObjectKind::Hold(HoldNote { duration: 1000.0 })),
+                object(8500.0, HitObjectKind::Circle)
+            ],
+            ..Beatmap::default()
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn works_out_end_times() {
+        let beatmap = beatmap();
+        let ends: Vec<f64> = beatmap.hit_objects.iter().map(|h| end_time(&beatmap, h)).collect();
+        // the first slider is before the first timing point and uses it anyway
+        let expected = [1000.0, 1000.0, 3000.0, 3750.0, 5000.0, 9000.0, 8500.0];
+        for (end, expected) in ends.into_iter().zip(expected) {
+            assert_close(end, expected);
+        }
+    }
+
+    #[test]
+    fn finds_control_points() {
+        let points = beatmap().effect_points;
+        let kiai = |time| point_at(&points, time, |p| p.time).map(|p| p.kiai);
+        assert_eq!(kiai(1999.0), None);
+        assert_eq!(kiai(2000.0), Some(true));
+        assert_eq!(kiai(3999.0), Some(true));
+        assert_eq!(kiai(4000.0), Some(false));
+        assert_eq!(kiai(100000.0), Some(false));
+    }
+
+    #[test]
+    fn tracks_progress() {
+        let beatmap = beatmap();
+        // the hold note ends last
+        let progress = MapProgress::new(&beatmap, 2500, 1.0).unwrap();
+        assert_close(progress.length, 8.5);
+        assert_close(progress.elapsed, 2.0);
+        assert_close(progress.remaining, 6.5);
+        assert_close(progress.percent, 2.0 / 8.5);
+        assert!(progress.kiai && !progress.in_break);
+
+        let progress = MapProgress::new(&beatmap, 6000, 1.0).unwrap();
+        assert!(!progress.kiai && progress.in_break);
+        assert!(!MapProgress::new(&beatmap, 8000, 1.0).unwrap().in_break);
+
+        // before the first object and after the last
+        let progress = MapProgress::new(&beatmap, -1000, 1.0).unwrap();
+        assert_eq!((progress.elapsed, progress.percent, progress.kiai), (0.0, 0.0, false));
+        let progress = MapProgress::new(&beatmap, 20000, 1.0).unwrap();
+        assert_eq!((progress.remaining, progress.percent), (0.0, 1.0));
+    }
+
+    #[test]
+    fn shows_real_time() {
+        let progress = MapProgress::new(&beatmap(), 3500, 1.5).unwrap();
+        assert_close(progress.length, 8.5 / 1.5);
+        assert_close(progress.elapsed, 2.0);
+        assert_close(progress.percent, 3.0 / 8.5);
+    }
+
+    #[test]
+    fn needs_objects() {
+        assert!(MapProgress::new(&Beatmap::default(), 0, 1.0).is_none());
+    }
+}
\ No newline at end of file
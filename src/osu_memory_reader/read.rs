@@ -1,23 +1,20 @@
-#[cfg(target_os = "windows")]
-use crate::osu_memory_reader::win::read_address;
-#[cfg(target_os = "windows")]
-use windows::Win32::Foundation::HANDLE;
+use crate::osu_memory_reader::error::MemoryError;
 
 use core::convert::From;
+use core::ops::Range;
 use paste::paste;
 
 macro_rules! read_bytes {
-    ($hproc:expr, $addr:expr, $b:literal) => {
+    ($reader:expr, $addr:expr, $b:literal) => {
         {
             let mut buf: [u8; $b] = [0; $b];
-            read_address($hproc, $addr, &mut buf, $b)?;
+            $reader.read_address($addr, &mut buf)?;
             buf
         }
     };
-    ($hproc:expr, $addr:expr, $buf:expr) => {
+    ($reader:expr, $addr:expr, $buf:expr) => {
         {
-            let len = $buf.len();
-            read_address($hproc, $addr, &mut $buf, len)?;
+            $reader.read_address($addr, &mut $buf)?;
         }
     }
 }
@@ -25,14 +22,14 @@ macro_rules! read_bytes {
 macro_rules! primitive_read_fn {
     ($t:ident) => {
         paste! {
-            unsafe fn [<read_ $t>](&self, addr: usize) -> Result<$t, String> {
+            unsafe fn [<read_ $t>](&self, addr: usize) -> Result<$t, MemoryError> {
                 if addr == 0 {
                     return Ok($t::default());
                 }
     
                 const SIZE: usize = core::mem::size_of::<$t>();
                 let mut buf: [u8; SIZE] = [0; SIZE];
-                read_address(self.handle(), addr, &mut buf, SIZE)?;
+                self.read_address(addr, &mut buf)?;
                 Ok($t::from_le_bytes(buf))
             }
         }
@@ -58,34 +55,66 @@ pub trait FromBytes {
     fn from_bytes(bytes: &[u8]) -> Self;
 }
 
+impl_primitive_from!(u32);
+impl_primitive_from!(i8);
 impl_primitive_from!(u16);
+impl_primitive_from!(i16);
 impl_primitive_from!(i32);
+impl_primitive_from!(f32);
+impl_primitive_from!(f64);
+
+// a copy of some of an object's fields taken with a single read
+pub struct MemoryBlock {
+    start: usize,
+    bytes: Vec<u8>
+}
+
+impl MemoryBlock {
+    // offset is from the object's address, same as with the read_* functions. a field
+    // outside the block is a wrong read_block range rather than anything osu! did
+    pub fn get<T: FromBytes>(&self, offset: usize) -> Result<T, MemoryError> {
+        let size = core::mem::size_of::<T>();
+        offset.checked_sub(self.start)
+            .and_then(|i| self.bytes.get(i..i+size))
+            .map(T::from_bytes)
+            .ok_or_else(|| MemoryError::InvalidData(format!(
+                "Field at {:X} is outside the block {:X}..{:X}", offset, self.start, self.start + self.bytes.len()
+            )))
+    }
+}
 
 pub trait MemoryReader {
-    fn handle(&self) -> HANDLE;
+    fn read_address(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError>;
 
-    unsafe fn read_raw(&self, addr: usize, len: usize) -> Result<Vec<u8>, String> {
+    unsafe fn read_raw(&self, addr: usize, len: usize) -> Result<Vec<u8>, MemoryError> {
         let mut buf: Vec<u8> = vec![0; len];
-        read_bytes!(self.handle(), addr, buf);
+        read_bytes!(self, addr, buf);
         Ok(buf)
     }
 
+    // the fields at offsets within the object at addr, for objects with several fields to read
+    unsafe fn read_block(&self, addr: usize, fields: Range<usize>) -> Result<MemoryBlock, MemoryError> {
+        if addr == 0 {
+            return Err(MemoryError::NullPointer(String::from("Object")));
+        }
+        Ok(MemoryBlock { start: fields.start, bytes: self.read_raw(addr + fields.start, fields.len())? })
+    }
+
     primitive_read_fn!(u32);
     primitive_read_fn!(i8);
     primitive_read_fn!(i16);
     primitive_read_fn!(i32);
-    primitive_read_fn!(f32);
     primitive_read_fn!(f64);
 
-    unsafe fn read_ptr(&self, addr: usize) -> Result<usize, String> {
+    unsafe fn read_ptr(&self, addr: usize) -> Result<usize, MemoryError> {
         let ptr = self.read_u32(addr)? as usize;
         if ptr == 0 {
-            return Err(String::from("Read null pointer"));
+            return Err(MemoryError::NullPointer(format!("Pointer at {:X}", addr)));
         }
         Ok(ptr)
     }
 
-    unsafe fn read_array<'a, T>(&self, addr: usize) -> Result<Vec<T>, String>
+    unsafe fn read_array<T>(&self, addr: usize) -> Result<Vec<T>, MemoryError>
     where
         T: Default,
         T: Clone,
@@ -110,16 +139,177 @@ pub trait MemoryReader {
         Ok(t_buf)
     }
 
-    unsafe fn read_str(&self, addr: usize) -> Result<String, String> {
+    // List<T> of values: items array at 0x4, count at 0xC
+    unsafe fn read_list<T>(&self, addr: usize, max_len: usize) -> Result<Vec<T>, MemoryError>
+    where
+        T: FromBytes
+    {
+        if addr == 0 {
+            return Ok(Vec::default());
+        }
+
+        let items = self.read_u32(addr+0x4)? as usize;
+        let len = self.read_i32(addr+0xC)?.max(0) as usize;
+        if len > max_len {
+            return Err(MemoryError::InvalidData(format!("List at {:X} has an invalid size of {}", addr, len)));
+        }
+        if len == 0 {
+            return Ok(Vec::default());
+        }
+
+        let size = core::mem::size_of::<T>();
+        let buf = self.read_raw(items+8, len*size)?;
+        Ok(buf.chunks_exact(size).map(T::from_bytes).collect())
+    }
+
+    // List<T> of references: items array at 0x4, count at 0xC
+    unsafe fn read_list_ptrs(&self, addr: usize, max_len: usize) -> Result<Vec<usize>, MemoryError> {
+        if addr == 0 {
+            return Ok(Vec::default());
+        }
+
+        let items = self.read_u32(addr+0x4)? as usize;
+        let len = self.read_i32(addr+0xC)?.max(0) as usize;
+        if len > max_len {
+            return Err(MemoryError::InvalidData(format!("List at {:X} has an invalid size of {}", addr, len)));
+        }
+        if len == 0 {
+            return Ok(Vec::default());
+        }
+
+        let buf = self.read_raw(items+8, len*4)?;
+        Ok(buf.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize).collect())
+    }
+
+    // the last count references of a List<T>, for lists that keep growing like the chat history
+    unsafe fn read_list_ptrs_tail(&self, addr: usize, count: usize) -> Result<Vec<usize>, MemoryError> {
+        if addr == 0 {
+            return Ok(Vec::default());
+        }
+
+        let items = self.read_u32(addr+0x4)? as usize;
+        let len = self.read_i32(addr+0xC)?.max(0) as usize;
+        if len == 0 {
+            return Ok(Vec::default());
+        }
+        // the items array is never shorter than the list
+        if items == 0 || len > self.read_u32(items+0x4)? as usize {
+            return Err(MemoryError::InvalidData(format!("List at {:X} has an invalid size of {}", addr, len)));
+        }
+
+        let start = len.saturating_sub(count);
+        let buf = self.read_raw(items+8+start*4, (len-start)*4)?;
+        Ok(buf.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize).collect())
+    }
+
+    unsafe fn read_str(&self, addr: usize) -> Result<String, MemoryError> {
         if addr == 0 {
             return Ok(String::default());
         }
 
         let buf = self.read_array::<u16>(addr)?;
 
-        match String::from_utf16(&buf) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(format!("Unicode decoding failed: {}", e.to_string()))
+        String::from_utf16(&buf).or(Err(MemoryError::InvalidUtf16(addr)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osu_memory_reader::mock::MockMemory;
+    use crate::osu_memory_reader::process::ProcessMemory;
+
+    struct MockReader(MockMemory);
+
+    impl MemoryReader for MockReader {
+        fn read_address(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError> {
+            self.0.read(addr, buf)
+        }
+    }
+
+    // a List<T> of references at addr, with room for capacity items
+    fn write_list(memory: &mut MockMemory, addr: usize, items: &[u32], capacity: usize) {
+        let array = memory.alloc(8 + capacity * 4);
+        let bytes: Vec<u8> = items.iter().flat_map(|i| i.to_le_bytes()).collect();
+        memory.write_array(array, 4, &bytes);
+        memory.write_u32(array + 4, capacity as u32);
+        memory.write_u32(addr + 0x4, array as u32).write_i32(addr + 0xC, items.len() as i32);
+    }
+
+    #[test]
+    fn reads_arrays() {
+        let mut memory = MockMemory::new();
+        let array = memory.alloc(0x20);
+        memory.write_array(array, 4, &[1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 7, 0, 0, 0]);
+        let reader = MockReader(memory);
+
+        unsafe {
+            assert_eq!(reader.read_array::<i32>(array).unwrap(), vec![1, -1, 7]);
+            assert!(reader.read_array::<i32>(0).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn reads_strings() {
+        let mut memory = MockMemory::new();
+        let text = memory.alloc(0x40);
+        let empty = memory.alloc(0x10);
+        let invalid = memory.alloc(0x10);
+        memory.write_str(text, "Tōkyō ☆ [Insane]");
+        memory.write_str(empty, "");
+        // a lone surrogate
+        memory.write_array(invalid, 2, &[0x00, 0xD8]);
+        let reader = MockReader(memory);
+
+        unsafe {
+            assert_eq!(reader.read_str(text).unwrap(), "Tōkyō ☆ [Insane]");
+            assert_eq!(reader.read_str(empty).unwrap(), "");
+            assert_eq!(reader.read_str(0).unwrap(), "");
+            assert!(matches!(reader.read_str(invalid), Err(MemoryError::InvalidUtf16(addr)) if addr == invalid));
+        }
+    }
+
+    #[test]
+    fn reads_the_end_of_lists() {
+        let mut memory = MockMemory::new();
+        let list = memory.alloc(0x10);
+        let garbage = memory.alloc(0x10);
+        write_list(&mut memory, list, &[10, 20, 30, 40, 50], 8);
+        write_list(&mut memory, garbage, &[1, 2, 3], 2);
+        let reader = MockReader(memory);
+
+        unsafe {
+            assert_eq!(reader.read_list_ptrs(list, 10).unwrap(), vec![10, 20, 30, 40, 50]);
+            assert!(reader.read_list_ptrs(list, 4).is_err());
+            assert_eq!(reader.read_list_ptrs_tail(list, 2).unwrap(), vec![40, 50]);
+            assert_eq!(reader.read_list_ptrs_tail(list, 10).unwrap(), vec![10, 20, 30, 40, 50]);
+            // more items than the array holds
+            assert!(matches!(reader.read_list_ptrs_tail(garbage, 2), Err(MemoryError::InvalidData(_))));
         }
     }
+
+    #[test]
+    fn reads_blocks() {
+        let mut memory = MockMemory::new();
+        let object = memory.alloc(0x20);
+        memory.write_i32(object + 0x8, -5).write_f64(object + 0x10, 1.5);
+        let reader = MockReader(memory);
+
+        let block = unsafe { reader.read_block(object, 0x8..0x18).unwrap() };
+        assert_eq!(block.get::<i32>(0x8).unwrap(), -5);
+        assert_eq!(block.get::<f64>(0x10).unwrap(), 1.5);
+        assert!(unsafe { reader.read_block(0, 0x8..0x18) }.is_err());
+    }
+
+    #[test]
+    fn catches_fields_outside_blocks() {
+        let mut memory = MockMemory::new();
+        let object = memory.alloc(0x20);
+        let reader = MockReader(memory);
+
+        let block = unsafe { reader.read_block(object, 0x8..0x10).unwrap() };
+        // the last 2 bytes are past the end
+        assert!(matches!(block.get::<i32>(0xE), Err(MemoryError::InvalidData(_))));
+        assert!(matches!(block.get::<i32>(0x4), Err(MemoryError::InvalidData(_))));
+    }
 }
\ No newline at end of file
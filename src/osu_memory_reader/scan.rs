@@ -0,0 +1,335 @@
+use crate::osu_memory_reader::process::{ MemoryRegion, ProcessMemory };
+use crate::osu_memory_reader::signatures::PatternValue;
+
+use memchr::{ memchr_iter, memchr2_iter, memchr3_iter };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::Mutex;
+
+// regions are read in chunks of this size so huge allocations don't have to be copied at once
+pub const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+// rough ranking of how common a byte is in x86 code and .NET heap data, higher is more common
+fn byte_weight(b: u8) -> u32 {
+    match b {
+        0x00 => 100,
+        0xFF => 80,
+        0x8B | 0x89 | 0x48 | 0xCC | 0x01 | 0x04 | 0x08 => 50,
+        0x02..=0x0F | 0x83 | 0x85 | 0xE8 | 0xC0 | 0x10 | 0x20 | 0x40 | 0x80 => 30,
+        _ => 10
+    }
+}
+
+struct CompiledPattern {
+    values: Vec<PatternValue>,
+    // offset of the rarest fixed byte, which is what candidates are found by
+    anchor: usize
+}
+
+impl CompiledPattern {
+    fn new(values: &[PatternValue]) -> Self {
+        let anchor = values.iter().enumerate()
+            .filter_map(|(i, v)| match v {
+                PatternValue::V(b) => Some((i, byte_weight(*b))),
+                PatternValue::A() => None
+            })
+            .min_by_key(|(_, weight)| *weight)
+            .map(|(i, _)| i)
+            .expect("pattern has no fixed bytes");
+
+        CompiledPattern { values: values.to_vec(), anchor }
+    }
+
+    fn anchor_byte(&self) -> u8 {
+        match self.values[self.anchor] {
+            PatternValue::V(b) => b,
+            PatternValue::A() => unreachable!()
+        }
+    }
+
+    fn matches_at(&self, data: &[u8], start: usize) -> bool {
+        if start + self.values.len() > data.len() {
+            return false;
+        }
+
+        self.values.iter().zip(&data[start..]).all(|(v, b)| match v {
+            PatternValue::V(v) => v == b,
+            PatternValue::A() => true
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternHit {
+    pub pattern: usize,
+    pub address: usize
+}
+
+// finds the first occurrence of many patterns in a single pass over a buffer
+pub struct Scanner {
+    patterns: Vec<CompiledPattern>,
+    by_anchor: Vec<Vec<usize>>,
+    max_len: usize
+}
+
+impl Scanner {
+    pub fn new(patterns: &[&[PatternValue]]) -> Self {
+        let patterns: Vec<CompiledPattern> = patterns.iter().map(|p| CompiledPattern::new(p)).collect();
+        let mut by_anchor = vec![Vec::new(); 256];
+        for (i, pattern) in patterns.iter().enumerate() {
+            by_anchor[pattern.anchor_byte() as usize].push(i);
+        }
+        let max_len = patterns.iter().map(|p| p.values.len()).max().unwrap_or(0);
+
+        Scanner { patterns, by_anchor, max_len }
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    // scans data that starts at base_address for every pattern still pending,
+    // marking found patterns as no longer pending
+    pub fn scan(&self, data: &[u8], base_address: usize, pending: &mut [bool]) -> Vec<PatternHit> {
+        let mut hits = Vec::new();
+        let mut remaining = pending.iter().filter(|p| **p).count();
+        if remaining == 0 {
+            return hits;
+        }
+
+        let mut anchors: Vec<u8> = self.patterns.iter().enumerate()
+            .filter(|(i, _)| pending[*i])
+            .map(|(_, p)| p.anchor_byte())
+            .collect();
+        anchors.sort_unstable();
+        anchors.dedup();
+
+        let mut check = |pos: usize, hits: &mut Vec<PatternHit>| -> bool {
+            for &i in &self.by_anchor[data[pos] as usize] {
+                let pattern = &self.patterns[i];
+                if !pending[i] || pos < pattern.anchor {
+                    continue;
+                }
+
+                let start = pos - pattern.anchor;
+                if pattern.matches_at(data, start) {
+                    pending[i] = false;
+                    remaining -= 1;
+                    hits.push(PatternHit { pattern: i, address: base_address + start });
+                }
+            }
+            remaining == 0
+        };
+
+        // with only a few distinct anchors left, let memchr skip over everything else
+        macro_rules! scan_positions {
+            ($positions:expr) => {
+                for pos in $positions {
+                    if check(pos, &mut hits) { break; }
+                }
+            };
+        }
+        match anchors[..] {
+            [a] => scan_positions!(memchr_iter(a, data)),
+            [a, b] => scan_positions!(memchr2_iter(a, b, data)),
+            [a, b, c] => scan_positions!(memchr3_iter(a, b, c, data)),
+            _ => scan_positions!((0..data.len()).filter(|&pos| !self.by_anchor[data[pos] as usize].is_empty()))
+        }
+
+        hits
+    }
+
+    // scans a whole region chunk by chunk, overlapping chunks so no match falls between them.
+    // regions can be freed or reprotected while scanning, so an unreadable chunk ends the
+    // scan with whatever was found before it
+    pub fn scan_region<P: ProcessMemory + ?Sized>(
+        &self,
+        process: &P,
+        region: &MemoryRegion,
+        pending: &mut [bool],
+        buf: &mut Vec<u8>
+    ) -> Vec<PatternHit> {
+        let mut hits = Vec::new();
+        let overlap = self.max_len.saturating_sub(1);
+        let mut offset = 0;
+        while offset < region.size && pending.iter().any(|p| *p) {
+            let len = CHUNK_SIZE.min(region.size - offset);
+            buf.resize(len, 0);
+            if process.read(region.base + offset, buf).is_err() {
+                break;
+            }
+            hits.extend(self.scan(buf, region.base + offset, pending));
+
+            if offset + len >= region.size {
+                break;
+            }
+            offset += len - overlap.min(len - 1);
+        }
+        hits
+    }
+
+    // scans regions on several threads and returns the lowest address hit for every pattern
+    pub fn scan_parallel<P: ProcessMemory + ?Sized>(
+        &self,
+        process: &P,
+        regions: &[MemoryRegion],
+        pending: &[bool],
+        threads: usize
+    ) -> Vec<PatternHit> {
+        let next_region = AtomicUsize::new(0);
+        let best: Mutex<Vec<Option<usize>>> = Mutex::new(vec![None; self.patterns.len()]);
+
+        std::thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| {
+                    let mut buf = Vec::new();
+                    loop {
+                        let i = next_region.fetch_add(1, Ordering::Relaxed);
+                        let region = match regions.get(i) {
+                            Some(r) => r,
+                            None => break
+                        };
+
+                        let mut region_pending = pending.to_vec();
+                        let hits = self.scan_region(process, region, &mut region_pending, &mut buf);
+
+                        let mut best = best.lock().unwrap();
+                        for hit in hits {
+                            let current = &mut best[hit.pattern];
+                            if current.is_none_or(|addr| hit.address < addr) {
+                                *current = Some(hit.address);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        best.into_inner().unwrap().into_iter().enumerate()
+            .filter_map(|(pattern, addr)| addr.map(|address| PatternHit { pattern, address }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osu_memory_reader::mock::MockMemory;
+
+    fn pattern(text: &str) -> Vec<PatternValue> {
+        text.split_whitespace()
+            .map(|b| match b {
+                "??" => PatternValue::A(),
+                b => PatternValue::V(u8::from_str_radix(b, 16).unwrap())
+            })
+            .collect()
+    }
+
+    fn scanner(patterns: &[&str]) -> Scanner {
+        let patterns: Vec<Vec<PatternValue>> = patterns.iter().map(|p| pattern(p)).collect();
+        Scanner::new(&patterns.iter().map(|p| p.as_slice()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn finds_several_patterns_in_one_pass() {
+        let scanner = scanner(&["48 83 F8 04", "0A D7 23 3C", "C3 ?? 5D"]);
+        let data = [0x00, 0x0A, 0xD7, 0x23, 0x3C, 0x90, 0x48, 0x83, 0xF8, 0x04, 0xC3, 0x11, 0x5D];
+        let mut pending = vec![true; 3];
+
+        let mut hits = scanner.scan(&data, 0x1000, &mut pending);
+        hits.sort_by_key(|hit| hit.pattern);
+        assert_eq!(hits, vec![
+            PatternHit { pattern: 0, address: 0x1006 },
+            PatternHit { pattern: 1, address: 0x1001 },
+            PatternHit { pattern: 2, address: 0x100A }
+        ]);
+        assert_eq!(pending, vec![false; 3]);
+    }
+
+    #[test]
+    fn finds_overlapping_matches() {
+        // the second pattern starts inside the first one's match
+        let scanner = scanner(&["E8 E8 E8 7F", "E8 7F 11"]);
+        let data = [0xE8, 0xE8, 0xE8, 0xE8, 0x7F, 0x11];
+        let mut pending = vec![true; 2];
+
+        let mut hits = scanner.scan(&data, 0, &mut pending);
+        hits.sort_by_key(|hit| hit.pattern);
+        assert_eq!(hits, vec![PatternHit { pattern: 0, address: 1 }, PatternHit { pattern: 1, address: 3 }]);
+    }
+
+    #[test]
+    fn only_reports_the_first_match() {
+        let scanner = scanner(&["7F 11"]);
+        let data = [0x7F, 0x11, 0x7F, 0x11];
+        let mut pending = vec![true];
+
+        assert_eq!(scanner.scan(&data, 0, &mut pending), vec![PatternHit { pattern: 0, address: 0 }]);
+        // found patterns aren't looked for again
+        assert!(scanner.scan(&data, 0, &mut pending).is_empty());
+    }
+
+    #[test]
+    fn anchors_skip_wildcards() {
+        // the rarest byte comes after wildcards, so the match starts before the anchor
+        let scanner = scanner(&["?? ?? 00 9D ?? 00"]);
+        let data = [0x9D, 0x00, 0x12, 0x34, 0x00, 0x9D, 0x56, 0x00];
+        let mut pending = vec![true];
+
+        assert_eq!(scanner.scan(&data, 0, &mut pending), vec![PatternHit { pattern: 0, address: 2 }]);
+        // an anchor too close to the start of the data for the pattern to fit
+        let mut pending = vec![true];
+        assert!(scanner.scan(&data[..2], 0, &mut pending).is_empty());
+        assert!(pending[0]);
+    }
+
+    #[test]
+    fn finds_patterns_straddling_chunks() {
+        let scanner = scanner(&["3C 00 ?? 01 A7"]);
+        let base = 0x10000;
+        let mut region = vec![0xFF; CHUNK_SIZE + 0x100];
+        // starts 3 bytes before the first chunk ends
+        let start = CHUNK_SIZE - 3;
+        region[start..start + 5].copy_from_slice(&[0x3C, 0x00, 0x42, 0x01, 0xA7]);
+        let mut memory = MockMemory::new();
+        memory.write(base, &region);
+
+        let region = MemoryRegion { base, size: region.len() };
+        let mut pending = vec![true];
+        let hits = scanner.scan_region(&memory, &region, &mut pending, &mut Vec::new());
+        assert_eq!(hits, vec![PatternHit { pattern: 0, address: base + start }]);
+    }
+
+    #[test]
+    fn keeps_hits_before_an_unreadable_chunk() {
+        let scanner = scanner(&["AB CD", "12 34"]);
+        let base = 0x10000;
+        let mut memory = MockMemory::new();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        chunk[..2].copy_from_slice(&[0xAB, 0xCD]);
+        memory.write(base, &chunk);
+
+        // the region's second chunk is gone
+        let region = MemoryRegion { base, size: CHUNK_SIZE * 2 };
+        let mut pending = vec![true, true];
+        let hits = scanner.scan_region(&memory, &region, &mut pending, &mut Vec::new());
+        assert_eq!(hits, vec![PatternHit { pattern: 0, address: base }]);
+        assert_eq!(pending, vec![false, true]);
+    }
+
+    #[test]
+    fn parallel_scan_takes_the_lowest_hit() {
+        let scanner = scanner(&["AB CD", "12 34"]);
+        let mut memory = MockMemory::new();
+        memory.write(0x1000, &[0x00, 0xAB, 0xCD]);
+        memory.write(0x3000, &[0xAB, 0xCD, 0x12, 0x34]);
+        memory.write(0x5000, &[0x12, 0x34]);
+        let regions = memory.regions().unwrap();
+
+        let mut hits = scanner.scan_parallel(&memory, &regions, &[true, true], 3);
+        hits.sort_by_key(|hit| hit.pattern);
+        assert_eq!(hits, vec![PatternHit { pattern: 0, address: 0x1001 }, PatternHit { pattern: 1, address: 0x3002 }]);
+
+        // patterns that aren't pending are left alone
+        assert_eq!(scanner.scan_parallel(&memory, &regions, &[false, true], 2).len(), 1);
+    }
+}
\ No newline at end of file
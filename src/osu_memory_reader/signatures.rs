@@ -0,0 +1,177 @@
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::mem::AddressInfo;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+const DEFAULT_SIGNATURES: &str = include_str!("signatures.txt");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternValue {
+    V(u8), // value
+    A() // any
+}
+
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub name: String,
+    pub pattern: Vec<PatternValue>
+}
+
+impl Signature {
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= self.pattern.len() && self.pattern.iter().zip(bytes).all(|(v, b)| match v {
+            PatternValue::V(v) => v == b,
+            PatternValue::A() => true
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PointerChain {
+    pub base: String,
+    pub offsets: Vec<isize>
+}
+
+#[derive(Debug, Clone)]
+pub struct Signatures {
+    pub patterns: Vec<Signature>,
+    pub chains: HashMap<String, PointerChain>
+}
+
+impl Default for Signatures {
+    fn default() -> Self {
+        Signatures::parse(DEFAULT_SIGNATURES, None).expect("compiled-in signatures are invalid")
+    }
+}
+
+fn parse_pattern(text: &str) -> Result<Vec<PatternValue>, String> {
+    let mut pattern = Vec::new();
+    for byte in text.split_whitespace() {
+        if byte == "?" || byte == "??" {
+            pattern.push(PatternValue::A());
+            continue;
+        }
+
+        match u8::from_str_radix(byte, 16) {
+            Ok(v) if byte.len() <= 2 => pattern.push(PatternValue::V(v)),
+            _ => return Err(format!("Invalid pattern byte '{}'", byte))
+        }
+    }
+
+    if pattern.is_empty() {
+        return Err(String::from("Pattern is empty"));
+    }
+    if pattern.iter().all(|v| *v == PatternValue::A()) {
+        return Err(String::from("Pattern only contains wildcards"));
+    }
+    Ok(pattern)
+}
+
+fn parse_offset(text: &str) -> Result<isize, String> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text))
+    };
+
+    let value = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16),
+        None => digits.parse::<isize>()
+    }.or(Err(format!("Invalid offset '{}'", text)))?;
+
+    Ok(if negative { -value } else { value })
+}
+
+impl Signatures {
+    // parses a signatures file, starting from `base` so that only changed entries have to be given
+    pub fn parse(text: &str, base: Option<&Signatures>) -> Result<Self, String> {
+        let mut signatures = match base {
+            Some(base) => base.clone(),
+            None => Signatures { patterns: Vec::new(), chains: HashMap::new() }
+        };
+
+        let mut section = "";
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split_once('#').map(|(l, _)| l).unwrap_or(line).trim();
+            if line.is_empty() { continue; }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = match &line[1..line.len()-1] {
+                    "patterns" => "patterns",
+                    "chains" => "chains",
+                    other => return Err(format!("Line {}: unknown section '{}'", i + 1, other))
+                };
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or(format!("Line {}: expected name = value", i + 1))?;
+            let name = name.trim();
+            let value = value.trim();
+            let err = |e: String| format!("Line {}: {}", i + 1, e);
+
+            match section {
+                "patterns" => {
+                    let pattern = parse_pattern(value).map_err(err)?;
+                    match signatures.patterns.iter_mut().find(|s| s.name == name) {
+                        Some(signature) => signature.pattern = pattern,
+                        None => signatures.patterns.push(Signature { name: name.into(), pattern })
+                    }
+                },
+                "chains" => {
+                    let mut parts = value.split_whitespace();
+                    let base = parts.next().ok_or(err(String::from("chain is missing a base")))?;
+                    let offsets = parts.map(parse_offset).collect::<Result<Vec<isize>, String>>().map_err(err)?;
+                    signatures.chains.insert(name.into(), PointerChain { base: base.into(), offsets });
+                },
+                _ => return Err(format!("Line {}: entry outside of a section", i + 1))
+            }
+        }
+
+        signatures.validate()?;
+        Ok(signatures)
+    }
+
+    // compiled-in signatures with signatures.txt applied over them, if it exists
+    pub fn load(path: &Path) -> Result<Self, MemoryError> {
+        let defaults = Signatures::default();
+        if !path.exists() {
+            return Ok(defaults);
+        }
+
+        let text = std::fs::read_to_string(path).map_err(|e| MemoryError::io(path, e))?;
+        Signatures::parse(&text, Some(&defaults)).map_err(|message| MemoryError::InvalidFile { path: path.to_path_buf(), message })
+    }
+
+    pub fn pattern(&self, name: &str) -> Option<&Signature> {
+        self.patterns.iter().find(|s| s.name == name)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for signature in &self.patterns {
+            if !AddressInfo::NAMES.contains(&signature.name.as_str()) {
+                return Err(format!("Unknown pattern '{}'", signature.name));
+            }
+        }
+
+        for (name, chain) in &self.chains {
+            if self.pattern(name).is_some() {
+                return Err(format!("'{}' is both a pattern and a chain", name));
+            }
+
+            // walk down to the pattern to reject unknown bases and cycles
+            let mut base = &chain.base;
+            let mut depth = 0;
+            while let Some(next) = self.chains.get(base) {
+                depth += 1;
+                if depth > self.chains.len() {
+                    return Err(format!("Chain '{}' refers back to itself", name));
+                }
+                base = &next.base;
+            }
+            if self.pattern(base).is_none() {
+                return Err(format!("Chain '{}' has an unknown base '{}'", name, base));
+            }
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
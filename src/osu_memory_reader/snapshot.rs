@@ -0,0 +1,275 @@
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::mock::MockMemory;
+use crate::osu_memory_reader::process::{ MemoryRegion, ProcessMemory };
+
+use std::collections::{ HashMap, HashSet };
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+
+// snapshot file layout (little endian):
+//   MAGIC, then a sequence of records each starting with a tag byte
+//   TAG_OSU_PATH: u32 len, utf8 path of the osu! folder
+//   TAG_FRAME:    u32 region count, then per region u64 address, u32 len, bytes
+//   TAG_FILE:     u32 len, utf8 path, u32 len, file contents
+//   TAG_WATCH_FRAME: same as TAG_FRAME, for a background poll like the watcher's, the chat
+//                    relay's or a tourney spectator's
+// the first frame holds the pattern hits from init, every following frame holds
+// the bytes read by one poll that changed since the previous frames
+const MAGIC: &[u8; 8] = b"OSUSNAP1";
+const TAG_OSU_PATH: u8 = 0;
+const TAG_FRAME: u8 = 1;
+const TAG_FILE: u8 = 2;
+const TAG_WATCH_FRAME: u8 = 3;
+
+pub struct SnapshotWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    frame: Vec<(usize, Vec<u8>)>,
+    state: MockMemory,
+    files: HashSet<PathBuf>
+}
+
+impl SnapshotWriter {
+    pub fn create(path: &Path, osu_path: &Path) -> Result<Self, MemoryError> {
+        let file = File::create(path).map_err(|e| MemoryError::io(path, e))?;
+        let mut writer = SnapshotWriter {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            frame: Vec::new(),
+            state: MockMemory::new(),
+            files: HashSet::new()
+        };
+
+        let osu_path = osu_path.to_string_lossy();
+        writer.write(MAGIC)?;
+        writer.write(&[TAG_OSU_PATH])?;
+        writer.write_bytes(osu_path.as_bytes())?;
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), MemoryError> {
+        self.file.write_all(bytes).map_err(|e| MemoryError::io(&self.path, e))
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), MemoryError> {
+        self.write(&(bytes.len() as u32).to_le_bytes())?;
+        self.write(bytes)
+    }
+
+    fn flush(&mut self) -> Result<(), MemoryError> {
+        self.file.flush().map_err(|e| MemoryError::io(&self.path, e))
+    }
+
+    pub fn record(&mut self, addr: usize, bytes: &[u8]) {
+        let mut known = vec![0; bytes.len()];
+        if self.state.read(addr, &mut known).is_ok() && known == bytes {
+            return;
+        }
+
+        self.state.write(addr, bytes);
+        self.frame.push((addr, bytes.to_vec()));
+    }
+
+    pub fn record_file(&mut self, path: &Path, contents: &[u8]) -> Result<(), MemoryError> {
+        if !self.files.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+
+        let path = path.to_string_lossy();
+        self.write(&[TAG_FILE])?;
+        self.write_bytes(path.as_bytes())?;
+        self.write_bytes(contents)?;
+        self.flush()
+    }
+
+    // watched frames are background polls, the others come from commands
+    pub fn finish_frame(&mut self, watched: bool) -> Result<(), MemoryError> {
+        let frame = std::mem::take(&mut self.frame);
+        self.write(&[if watched { TAG_WATCH_FRAME } else { TAG_FRAME }])?;
+        self.write(&(frame.len() as u32).to_le_bytes())?;
+        for (addr, bytes) in frame {
+            self.write(&(addr as u64).to_le_bytes())?;
+            self.write_bytes(&bytes)?;
+        }
+        self.flush()
+    }
+}
+
+impl Drop for SnapshotWriter {
+    fn drop(&mut self) {
+        if !self.frame.is_empty() {
+            let _ = self.finish_frame(false);
+        }
+    }
+}
+
+struct SnapshotReader<'a> {
+    path: &'a Path,
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn invalid(&self, message: &str) -> MemoryError {
+        MemoryError::InvalidFile { path: self.path.to_path_buf(), message: message.into() }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MemoryError> {
+        if self.pos + len > self.data.len() {
+            return Err(self.invalid("Snapshot file is truncated"));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, MemoryError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, MemoryError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], MemoryError> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    fn read_string(&mut self) -> Result<String, MemoryError> {
+        String::from_utf8(self.read_bytes()?.to_vec()).or(Err(self.invalid("Invalid string in snapshot file")))
+    }
+}
+
+struct Frame {
+    watched: bool,
+    reads: Vec<(usize, Vec<u8>)>
+}
+
+// serves a recorded session: each poll advances to the next frame recorded by a command,
+// going through the watcher's polls in between, since every frame only holds what changed
+pub struct ReplayMemory {
+    pub osu_path: PathBuf,
+    frames: Vec<Frame>,
+    files: HashMap<PathBuf, Vec<u8>>,
+    memory: MockMemory,
+    next_frame: usize
+}
+
+impl ReplayMemory {
+    pub fn open(path: &Path) -> Result<Self, MemoryError> {
+        let data = std::fs::read(path).map_err(|e| MemoryError::io(path, e))?;
+        let mut reader = SnapshotReader { path, data: &data, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(reader.invalid("Not a snapshot file"));
+        }
+
+        let mut osu_path = PathBuf::new();
+        let mut frames = Vec::new();
+        let mut files = HashMap::new();
+        while reader.pos < data.len() {
+            match reader.take(1)?[0] {
+                TAG_OSU_PATH => osu_path = PathBuf::from(reader.read_string()?),
+                tag @ (TAG_FRAME | TAG_WATCH_FRAME) => {
+                    let count = reader.read_u32()?;
+                    // the count isn't trusted for an allocation, a corrupt one runs out of data instead
+                    let mut reads = Vec::new();
+                    for _ in 0..count {
+                        let addr = reader.read_u64()? as usize;
+                        reads.push((addr, reader.read_bytes()?.to_vec()));
+                    }
+                    frames.push(Frame { watched: tag == TAG_WATCH_FRAME, reads });
+                },
+                TAG_FILE => {
+                    let path = PathBuf::from(reader.read_string()?);
+                    files.insert(path, reader.read_bytes()?.to_vec());
+                },
+                tag => return Err(reader.invalid(&format!("Unknown record {} in snapshot file", tag)))
+            }
+        }
+
+        let mut replay = ReplayMemory {
+            osu_path,
+            frames,
+            files,
+            memory: MockMemory::new(),
+            next_frame: 0
+        };
+        replay.apply_next_frame();
+        Ok(replay)
+    }
+
+    // frames recorded by commands, which are the ones a replay steps through
+    pub fn frame_count(&self) -> usize {
+        self.frames.iter().filter(|frame| !frame.watched).count()
+    }
+
+    // command frames still to be served, not counting the one currently loaded
+    pub fn remaining_frames(&self) -> usize {
+        self.frames[self.next_frame..].iter().filter(|frame| !frame.watched).count()
+    }
+
+    fn apply_next_frame(&mut self) {
+        while let Some(frame) = self.frames.get(self.next_frame) {
+            for (addr, bytes) in &frame.reads {
+                self.memory.write(*addr, bytes);
+            }
+            self.next_frame += 1;
+            if !frame.watched {
+                break;
+            }
+        }
+    }
+}
+
+impl ProcessMemory for ReplayMemory {
+    fn regions(&self) -> Result<Vec<MemoryRegion>, MemoryError> {
+        self.memory.regions()
+    }
+
+    fn read(&self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryError> {
+        self.memory.read(addr, buf)
+    }
+
+    fn begin_poll(&mut self) {
+        self.apply_next_frame();
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>, MemoryError> {
+        match self.files.get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => Err(MemoryError::Io { path: path.to_path_buf(), message: String::from("not recorded in the snapshot") })
+        }
+    }
+
+    // recorded files never change
+    fn file_modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_a_corrupt_region_count() {
+        let path = std::env::temp_dir().join(format!("osu_twitch_bot-{}-corrupt.osusnap", std::process::id()));
+        let mut data = MAGIC.to_vec();
+        data.push(TAG_FRAME);
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0x1000u64.to_le_bytes());
+        std::fs::write(&path, &data).unwrap();
+
+        let result = ReplayMemory::open(&path);
+        let _ = std::fs::remove_file(&path);
+        match result {
+            Err(MemoryError::InvalidFile { message, .. }) => assert_eq!(message, "Snapshot file is truncated"),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("corrupt snapshot was accepted")
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,342 @@
+use crate::osu_memory_reader::mem::{ MemoryData, ResultsMemoryData };
+use crate::osu_memory_reader::mods::Mods;
+
+// osu!'s OsuModes, the value behind the status pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OsuStatus {
+    MainMenu,
+    Editing,
+    Playing,
+    GameShutdown,
+    SongSelectEdit,
+    SongSelect,
+    SelectDrawings,
+    ResultsScreen,
+    GameStartup,
+    Busy,
+    Unknown,
+    MultiplayerLobby,
+    MultiplayerRoom,
+    MultiplayerSongSelect,
+    MultiplayerResults,
+    OsuDirect,
+    OffsetWizard,
+    MultiplayerResultsTagCoop,
+    MultiplayerResultsTeam,
+    BeatmapImport,
+    PackageUpdater,
+    Benchmark,
+    Tourney,
+    Charts,
+    // a value osu! added after these, or garbage from a bad status address
+    Other(u32)
+}
+
+impl OsuStatus {
+    pub fn from_u32(value: u32) -> Self {
+        match value {
+            0 => OsuStatus::MainMenu,
+            1 => OsuStatus::Editing,
+            2 => OsuStatus::Playing,
+            3 => OsuStatus::GameShutdown,
+            4 => OsuStatus::SongSelectEdit,
+            5 => OsuStatus::SongSelect,
+            6 => OsuStatus::SelectDrawings,
+            7 => OsuStatus::ResultsScreen,
+            8 => OsuStatus::GameStartup,
+            9 => OsuStatus::Busy,
+            10 => OsuStatus::Unknown,
+            11 => OsuStatus::MultiplayerLobby,
+            12 => OsuStatus::MultiplayerRoom,
+            13 => OsuStatus::MultiplayerSongSelect,
+            14 => OsuStatus::MultiplayerResults,
+            15 => OsuStatus::OsuDirect,
+            16 => OsuStatus::OffsetWizard,
+            17 => OsuStatus::MultiplayerResultsTagCoop,
+            18 => OsuStatus::MultiplayerResultsTeam,
+            19 => OsuStatus::BeatmapImport,
+            20 => OsuStatus::PackageUpdater,
+            21 => OsuStatus::Benchmark,
+            22 => OsuStatus::Tourney,
+            23 => OsuStatus::Charts,
+            value => OsuStatus::Other(value)
+        }
+    }
+
+    pub fn is_results(&self) -> bool {
+        matches!(
+            self,
+            OsuStatus::ResultsScreen |
+            OsuStatus::MultiplayerResults |
+            OsuStatus::MultiplayerResultsTagCoop |
+            OsuStatus::MultiplayerResultsTeam
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    StatusChanged { from: OsuStatus, to: OsuStatus },
+    BeatmapChanged { beatmap_id: i32, md5: String },
+    ModsChanged { from: Mods, to: Mods },
+    PlayStarted,
+    // quick retry, which restarts the map without leaving the playing status
+    PlayRetried,
+    PlayFailed,
+    // none on results screens that aren't read, like multiplayer ones
+    PlayCompleted(Option<ResultsMemoryData>),
+    // left gameplay without completing the map, by quitting or after failing
+    PlayQuit,
+    // the combo that was lost
+    ComboBroken { combo: i16 }
+}
+
+// osu! only plays the combobreak sound from this combo, losing less isn't worth reporting
+const COMBO_BREAK_THRESHOLD: i16 = 20;
+
+// follows the game from poll to poll and reports what changed in between
+#[derive(Debug, Default)]
+pub struct GameStateTracker {
+    status: Option<OsuStatus>,
+    retries: i32,
+    play_time: i32,
+    // hp of the previous poll in this attempt, so the 0 hp while a map loads isn't taken as a fail
+    last_hp: f64,
+    failed: bool,
+    combo: i16,
+    md5: Option<String>,
+    mods: Option<Mods>
+}
+
+impl GameStateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> Option<OsuStatus> {
+        self.status
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn update(&mut self, data: &MemoryData) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let previous = self.status.replace(data.status);
+        if let Some(from) = previous {
+            if from != data.status {
+                events.push(GameEvent::StatusChanged { from, to: data.status });
+            }
+        }
+
+        // an empty md5 is osu! still loading the map
+        if !data.menu.md5.is_empty() && self.md5.as_ref() != Some(&data.menu.md5) {
+            let previous = self.md5.replace(data.menu.md5.clone());
+            // the first map seen isn't a change
+            if previous.is_some() {
+                events.push(GameEvent::BeatmapChanged { beatmap_id: data.menu.beatmap_id, md5: data.menu.md5.clone() });
+            }
+        }
+        let mods = data.current_mods();
+        if let Some(from) = self.mods.replace(mods) {
+            if from != mods {
+                events.push(GameEvent::ModsChanged { from, to: mods });
+            }
+        }
+
+        match (previous, data.status) {
+            (Some(OsuStatus::Playing), OsuStatus::Playing) => {
+                if let Some(ref gameplay) = data.gameplay {
+                    // play_time going back catches a retry the retry count missed between polls
+                    if gameplay.retries > self.retries || data.play_time < self.play_time {
+                        self.start_attempt();
+                        events.push(GameEvent::PlayRetried);
+                    } else {
+                        if gameplay.combo < self.combo && self.combo >= COMBO_BREAK_THRESHOLD {
+                            events.push(GameEvent::ComboBroken { combo: self.combo });
+                        }
+                        if !self.failed && self.last_hp > 0.0 && gameplay.hp <= 0.0
+                            && !gameplay.stats.mods.contains(Mods::NO_FAIL) {
+                            self.failed = true;
+                            events.push(GameEvent::PlayFailed);
+                        }
+                    }
+                }
+            },
+            (_, OsuStatus::Playing) => {
+                self.start_attempt();
+                events.push(GameEvent::PlayStarted);
+            },
+            (Some(OsuStatus::Playing), to) if to.is_results() && !self.failed => {
+                events.push(GameEvent::PlayCompleted(data.results.clone()));
+            },
+            (Some(OsuStatus::Playing), _) => events.push(GameEvent::PlayQuit),
+            _ => {}
+        }
+
+        self.play_time = data.play_time;
+        if let Some(ref gameplay) = data.gameplay {
+            self.retries = gameplay.retries;
+            self.last_hp = gameplay.hp;
+            self.combo = gameplay.combo;
+        }
+        events
+    }
+
+    fn start_attempt(&mut self) {
+        self.failed = false;
+        self.last_hp = 0.0;
+        self.combo = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osu_memory_reader::mem::{ GameplayMemoryData, MenuMemoryData, SettingsMemoryData };
+
+    #[test]
+    fn keeps_unlisted_values() {
+        assert_eq!(OsuStatus::from_u32(10), OsuStatus::Unknown);
+        assert_eq!(OsuStatus::from_u32(22), OsuStatus::Tourney);
+        assert_eq!(OsuStatus::from_u32(24), OsuStatus::Other(24));
+        assert_eq!(OsuStatus::from_u32(u32::MAX), OsuStatus::Other(u32::MAX));
+    }
+
+    fn poll(status: OsuStatus, play_time: i32, gameplay: Option<GameplayMemoryData>) -> MemoryData {
+        MemoryData {
+            status,
+            chat_visible: false,
+            play_time,
+            settings: SettingsMemoryData::default(),
+            tournament: None,
+            results: None,
+            menu: MenuMemoryData { md5: String::from("map"), beatmap_id: 1, ..MenuMemoryData::default() },
+            gameplay,
+            hit_error_stats: None,
+            user: None
+        }
+    }
+
+    fn playing(play_time: i32, retries: i32, combo: i16, hp: f64) -> MemoryData {
+        let gameplay = GameplayMemoryData { retries, combo, hp, ..GameplayMemoryData::default() };
+        poll(OsuStatus::Playing, play_time, Some(gameplay))
+    }
+
+    fn results() -> MemoryData {
+        let results = ResultsMemoryData { score: 727, ..ResultsMemoryData::default() };
+        MemoryData { results: Some(results), ..poll(OsuStatus::ResultsScreen, 0, None) }
+    }
+
+    // the events of every poll but the first, which only sets the tracker up
+    fn events(polls: &[MemoryData]) -> Vec<String> {
+        let mut tracker = GameStateTracker::new();
+        tracker.update(&polls[0]);
+        polls[1..].iter().flat_map(|data| tracker.update(data)).map(|event| format!("{:?}", event)).collect()
+    }
+
+    #[test]
+    fn follows_a_play_to_the_results() {
+        let polls = [
+            poll(OsuStatus::SongSelect, 0, None),
+            playing(-1500, 0, 0, 0.0),
+            playing(1000, 0, 10, 1.0),
+            results()
+        ];
+        let events = events(&polls);
+        assert_eq!(events[0], "StatusChanged { from: SongSelect, to: Playing }");
+        assert_eq!(events[1], "PlayStarted");
+        assert_eq!(events[2], "StatusChanged { from: Playing, to: ResultsScreen }");
+        assert!(events[3].starts_with("PlayCompleted(Some(ResultsMemoryData {") && events[3].contains("score: 727"));
+        assert_eq!(events.len(), 4);
+    }
+
+    #[test]
+    fn notices_retries() {
+        // the retry count going up, then only play_time going back
+        let polls = [playing(30000, 0, 100, 1.0), playing(100, 1, 0, 0.0), playing(20000, 1, 80, 1.0), playing(500, 1, 0, 0.0)];
+        assert_eq!(events(&polls), ["PlayRetried", "PlayRetried"]);
+    }
+
+    #[test]
+    fn notices_fails() {
+        let polls = [
+            playing(1000, 0, 0, 0.0),
+            playing(2000, 0, 5, 0.5),
+            playing(3000, 0, 0, 0.0),
+            playing(3100, 0, 0, 0.0),
+            poll(OsuStatus::SongSelect, 0, None)
+        ];
+        assert_eq!(events(&polls), [
+            "PlayFailed",
+            "StatusChanged { from: Playing, to: SongSelect }",
+            "PlayQuit"
+        ]);
+
+        // a failed play that still ends up on a results screen, like in multiplayer, didn't complete
+        let polls = [playing(2000, 0, 5, 0.5), playing(3000, 0, 0, 0.0), results()];
+        assert_eq!(events(&polls), ["PlayFailed", "StatusChanged { from: Playing, to: ResultsScreen }", "PlayQuit"]);
+    }
+
+    #[test]
+    fn ignores_empty_hp_with_no_fail() {
+        let mut polls = [playing(2000, 0, 5, 0.5), playing(3000, 0, 0, 0.0)];
+        for data in &mut polls {
+            data.gameplay.as_mut().unwrap().stats.mods = Mods::NO_FAIL;
+        }
+        assert!(events(&polls).is_empty());
+    }
+
+    #[test]
+    fn notices_quitting() {
+        let polls = [playing(5000, 0, 50, 1.0), poll(OsuStatus::SongSelect, 0, None)];
+        assert_eq!(events(&polls), ["StatusChanged { from: Playing, to: SongSelect }", "PlayQuit"]);
+    }
+
+    #[test]
+    fn reports_combo_breaks_from_the_threshold() {
+        let polls = [
+            playing(1000, 0, 19, 1.0),
+            playing(2000, 0, 0, 1.0),
+            playing(3000, 0, 20, 1.0),
+            playing(4000, 0, 1, 1.0)
+        ];
+        assert_eq!(events(&polls), ["ComboBroken { combo: 20 }"]);
+    }
+
+    #[test]
+    fn reports_map_and_mod_changes() {
+        let mut other = poll(OsuStatus::SongSelect, 0, None);
+        other.menu = MenuMemoryData { md5: String::from("other"), beatmap_id: 2, mods: Mods::HIDDEN, ..MenuMemoryData::default() };
+        let mut loading = poll(OsuStatus::SongSelect, 0, None);
+        loading.menu.md5.clear();
+        loading.menu.mods = Mods::HIDDEN;
+        let polls = [poll(OsuStatus::SongSelect, 0, None), other, loading];
+        assert_eq!(events(&polls), [
+            String::from("BeatmapChanged { beatmap_id: 2, md5: \"other\" }"),
+            format!("{:?}", GameEvent::ModsChanged { from: Mods::NONE, to: Mods::HIDDEN })
+        ]);
+    }
+
+    // the watcher's PlayCompleted with results is what the UR announcement goes out on
+    #[test]
+    fn completes_only_plays_that_reach_a_read_results_screen() {
+        let completed = |polls: &[MemoryData]| events(polls).iter().filter(|e| e.starts_with("PlayCompleted")).cloned().collect::<Vec<String>>();
+
+        let polls = [playing(1000, 0, 10, 1.0), results()];
+        assert!(completed(&polls)[0].starts_with("PlayCompleted(Some("));
+        // multiplayer results aren't read
+        let polls = [playing(1000, 0, 10, 1.0), poll(OsuStatus::MultiplayerResults, 0, None)];
+        assert_eq!(completed(&polls), ["PlayCompleted(None)"]);
+
+        let failed = [playing(1000, 0, 10, 0.5), playing(2000, 0, 0, 0.0), results()];
+        let quit = [playing(1000, 0, 10, 1.0), poll(OsuStatus::SongSelect, 0, None), results()];
+        // the results screen of a score picked from song select
+        let from_menu = [poll(OsuStatus::SongSelect, 0, None), results()];
+        let retried = [playing(30000, 0, 10, 1.0), playing(100, 1, 0, 0.0)];
+        for polls in [&failed[..], &quit, &from_menu, &retried] {
+            assert!(completed(polls).is_empty());
+        }
+    }
+}
\ No newline at end of file
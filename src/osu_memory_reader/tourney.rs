@@ -0,0 +1,231 @@
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::mem::{ MemoryClient, TournamentMemoryData };
+use crate::osu_memory_reader::mods::Mods;
+use crate::osu_memory_reader::process::{ NativeProcess, ProcessInfo, ProcessMemory };
+use crate::osu_memory_reader::status::OsuStatus;
+
+// what a tourney spectator client shows of the player in its slot
+#[derive(Debug, Clone)]
+pub struct TourneyClientData {
+    pub player_name: String,
+    pub score: i32,
+    pub combo: i16,
+    pub accuracy: f64,
+    pub mods: Mods
+}
+
+// live scores of both teams, none for a client that isn't playing right now
+#[derive(Debug)]
+pub struct TourneyScores {
+    pub team_one: Vec<Option<TourneyClientData>>,
+    pub team_two: Vec<Option<TourneyClientData>>,
+    // the totals on the manager's score bar, none while it's hidden
+    pub manager_scores: Option<(i32, i32)>
+}
+
+impl TourneyScores {
+    // the manager's own total when it shows one, otherwise the clients' scores added up
+    pub fn team_one_score(&self) -> i64 {
+        match self.manager_scores {
+            Some((score, _)) => score as i64,
+            None => self.team_one.iter().flatten().map(|c| c.score as i64).sum()
+        }
+    }
+
+    pub fn team_two_score(&self) -> i64 {
+        match self.manager_scores {
+            Some((_, score)) => score as i64,
+            None => self.team_two.iter().flatten().map(|c| c.score as i64).sum()
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.team_one.iter().chain(&self.team_two).any(|c| c.is_some())
+    }
+}
+
+// the spectator clients of a tourney, in slot order
+pub struct TourneyClients<P: ProcessMemory = NativeProcess> {
+    pub clients: Vec<MemoryClient<P>>
+}
+
+impl<P: ProcessMemory> TourneyClients<P> {
+    // picks the tourney manager out of all attached osu! clients and returns it with the
+    // spectator clients. the manager's ipc block has no known layout for its slots, but it
+    // starts the spectator clients from its own osu!.exe one after another in slot order,
+    // so their start times give the slots. without a manager the longest running client is
+    // used on its own
+    pub fn split(mut clients: Vec<(ProcessInfo, MemoryClient<P>)>) -> Result<(MemoryClient<P>, TourneyClients<P>), MemoryError> {
+        if clients.is_empty() {
+            return Err(MemoryError::ProcessNotFound("osu!.exe".into()));
+        }
+        clients.sort_by_key(|(info, _)| info.start_time);
+
+        let manager = clients.iter_mut().position(|(_, client)| matches!(
+            client.get_memory_data(),
+            Ok(data) if data.status == OsuStatus::Tourney && data.tournament.as_ref().is_some_and(|t| !t.team_one_name.is_empty())
+        ));
+        let (manager_info, manager) = match manager {
+            Some(i) => clients.remove(i),
+            None => return Ok((clients.remove(0).1, TourneyClients { clients: Vec::new() }))
+        };
+
+        // anything running before the manager, or from another install like the streamer's
+        // own osu!, isn't one of its spectators
+        let clients = clients.into_iter()
+            .filter(|(info, _)| info.start_time >= manager_info.start_time && info.exe_path == manager_info.exe_path)
+            .map(|(_, client)| client)
+            .collect();
+        Ok((manager, TourneyClients { clients }))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    // the first half of the slots is team one (left), the second half team two (right)
+    pub fn get_scores(&mut self, tournament: &TournamentMemoryData) -> TourneyScores {
+        let half = self.clients.len().div_ceil(2);
+        let mut scores: Vec<Option<TourneyClientData>> = self.clients.iter_mut()
+            .map(|client| client.get_tourney_client_data().ok().flatten())
+            .collect();
+        let team_two = scores.split_off(half);
+
+        TourneyScores {
+            team_one: scores,
+            team_two,
+            manager_scores: (tournament.score_visible != 0).then_some((tournament.team_one_score, tournament.team_two_score))
+        }
+    }
+}
+
+impl TourneyClients {
+    // the slots come from the clients' start times, so a client that closed means they
+    // need working out again rather than the rest moving up a slot
+    pub fn all_alive(&self) -> bool {
+        self.clients.iter().all(|client| client.process.is_alive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osu_memory_reader::mem::tests::{ song_select, Layout };
+    use crate::osu_memory_reader::mock::MockMemory;
+
+    use std::path::PathBuf;
+
+    const TOURNEY: &str = "C:/osu!tourney/osu!.exe";
+
+    // a tourney manager showing the team totals, or not
+    fn manager(scores: Option<(i32, i32)>) -> Layout {
+        let mut layout = song_select();
+        layout.set_status(22);
+        let ruleset = layout.ruleset();
+        let (one_score, two_score) = scores.unwrap_or((0, 0));
+        for (offset, name, score) in [(0x1C, "Red", one_score), (0x20, "Blue", two_score)] {
+            let team = layout.object_at(ruleset + offset, 0x40);
+            layout.memory.write_i32(team + 0x28, score).write_i32(team + 0x2C, 2).write_i32(team + 0x30, 7);
+            layout.memory.write_i8(team + 0x39, scores.is_some() as i8);
+            let header = layout.object_at(team + 0x20, 0x148);
+            layout.str_at(header + 0x144, name);
+        }
+        let ipc = layout.object_at(ruleset + 0x34, 0x8);
+        layout.object_at(ipc + 0x4, 0x8);
+        layout
+    }
+
+    // a spectator client, playing when given the player's name and score
+    fn spectator(play: Option<(&str, i32)>) -> Layout {
+        let mut layout = song_select();
+        if let Some((name, score)) = play {
+            let ruleset = layout.ruleset();
+            layout.memory.write_i32(ruleset + 0x100, score);
+            let base = layout.object_at(ruleset + 0x68, 0x50);
+            let ruleset1 = layout.object_at(base + 0x38, 0xA0);
+            layout.memory.write_i16(ruleset1 + 0x94, 100);
+            layout.str_at(ruleset1 + 0x28, name);
+            let mods = layout.object_at(ruleset1 + 0x1C, 0x10);
+            layout.memory.write_u32(mods + 0xC, Mods::HIDDEN.bits());
+            let accuracy = layout.object_at(base + 0x48, 0x14);
+            layout.memory.write_f64(accuracy + 0xC, 98.5);
+        }
+        layout
+    }
+
+    fn process(exe_path: &str, start_time: u64) -> ProcessInfo {
+        ProcessInfo { proc_id: start_time as u32, exe_path: PathBuf::from(exe_path), start_time }
+    }
+
+    // each client's osu_path names it
+    fn names(clients: &[MemoryClient<MockMemory>]) -> Vec<&str> {
+        clients.iter().map(|client| client.osu_path.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn finds_the_manager_and_its_spectators() {
+        let clients = vec![
+            (process(TOURNEY, 130), spectator(None).client("slot 2")),
+            (process("C:/osu!/osu!.exe", 50), spectator(None).client("own osu!")),
+            (process(TOURNEY, 100), manager(None).client("manager")),
+            (process(TOURNEY, 110), spectator(None).client("slot 1")),
+            // another install started mid-match
+            (process("C:/osu!/osu!.exe", 120), spectator(None).client("own osu! restarted")),
+            (process(TOURNEY, 90), spectator(None).client("left over"))
+        ];
+        let (manager, spectators) = TourneyClients::split(clients).unwrap();
+        assert_eq!(manager.osu_path.to_str(), Some("manager"));
+        assert_eq!(names(&spectators.clients), ["slot 1", "slot 2"]);
+    }
+
+    #[test]
+    fn uses_the_oldest_client_without_a_manager() {
+        let clients = vec![
+            (process("C:/osu!/osu!.exe", 200), spectator(None).client("newer")),
+            (process("D:/osu!/osu!.exe", 100), spectator(None).client("older"))
+        ];
+        let (client, spectators) = TourneyClients::split(clients).unwrap();
+        assert_eq!(client.osu_path.to_str(), Some("older"));
+        assert!(spectators.is_empty());
+
+        assert!(matches!(TourneyClients::<MockMemory>::split(Vec::new()), Err(e) if e.is_process_gone()));
+    }
+
+    fn scores(manager_scores: Option<(i32, i32)>, players: &[Option<(&str, i32)>]) -> TourneyScores {
+        let mut manager = manager(manager_scores).client("manager");
+        let tournament = manager.get_memory_data().unwrap().tournament.unwrap();
+        let mut clients = TourneyClients {
+            clients: players.iter().map(|play| spectator(*play).client("")).collect()
+        };
+        clients.get_scores(&tournament)
+    }
+
+    #[test]
+    fn adds_up_team_scores() {
+        let scores = scores(None, &[Some(("a", 1000)), Some(("b", 2000)), Some(("c", 500)), None]);
+        assert_eq!((scores.team_one_score(), scores.team_two_score()), (3000, 500));
+        assert!(scores.is_playing());
+
+        let a = scores.team_one[0].as_ref().unwrap();
+        assert_eq!((a.player_name.as_str(), a.score, a.combo, a.accuracy, a.mods), ("a", 1000, 100, 98.5, Mods::HIDDEN));
+        assert!(scores.team_two[1].is_none());
+    }
+
+    #[test]
+    fn splits_odd_slots_towards_team_one() {
+        let scores = scores(None, &[Some(("a", 1)), Some(("b", 2)), Some(("c", 4))]);
+        assert_eq!((scores.team_one.len(), scores.team_two.len()), (2, 1));
+        assert_eq!((scores.team_one_score(), scores.team_two_score()), (3, 4));
+    }
+
+    #[test]
+    fn prefers_the_managers_totals() {
+        let shown = scores(Some((123456, 654321)), &[Some(("a", 1000)), Some(("b", 2000))]);
+        assert_eq!(shown.manager_scores, Some((123456, 654321)));
+        assert_eq!((shown.team_one_score(), shown.team_two_score()), (123456, 654321));
+
+        let idle = scores(None, &[None, None]);
+        assert_eq!(idle.manager_scores, None);
+        assert!(!idle.is_playing());
+    }
+}
\ No newline at end of file
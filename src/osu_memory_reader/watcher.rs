@@ -0,0 +1,76 @@
+use crate::osu_memory_reader::mem::MemoryClient;
+use crate::osu_memory_reader::process::{ NativeProcess, ProcessMemory };
+use crate::osu_memory_reader::status::{ GameEvent, GameStateTracker };
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{ broadcast, Mutex };
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+// how many events a subscriber can fall behind before it starts missing them
+const EVENT_CAPACITY: usize = 64;
+
+// polls the game in the background and publishes what changed between polls.
+// the client stays usable for on demand reads through client()
+pub struct MemoryWatcher<P: ProcessMemory = NativeProcess> {
+    client: Arc<Mutex<MemoryClient<P>>>,
+    events: broadcast::Sender<GameEvent>,
+    task: JoinHandle<()>
+}
+
+impl<P: ProcessMemory + Send + 'static> MemoryWatcher<P> {
+    pub fn spawn(client: MemoryClient<P>, interval: Duration) -> Self {
+        let client = Arc::new(Mutex::new(client));
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        let task = tokio::spawn(MemoryWatcher::poll(client.clone(), events.clone(), interval));
+        MemoryWatcher { client, events, task }
+    }
+
+    async fn poll(client: Arc<Mutex<MemoryClient<P>>>, events: broadcast::Sender<GameEvent>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut tracker = GameStateTracker::new();
+        loop {
+            ticker.tick().await;
+            // a poll can parse a new map for live pp, which would hold up everything else
+            let polled = client.clone();
+            let data = match tokio::task::spawn_blocking(move || polled.blocking_lock().watch_memory_data()).await {
+                Ok(Ok(data)) => data,
+                // nothing left to watch
+                Ok(Err(e)) if e.is_process_gone() => break,
+                // reads fail now and then while osu! switches screens, the next poll will catch up
+                Ok(Err(_)) => continue,
+                // the poll panicked, attaching again starts from a clean slate
+                Err(_) => break
+            };
+
+            for event in tracker.update(&data) {
+                // nobody listening is fine
+                let _ = events.send(event);
+            }
+        }
+    }
+}
+
+impl<P: ProcessMemory> MemoryWatcher<P> {
+    pub fn client(&self) -> &Arc<Mutex<MemoryClient<P>>> {
+        &self.client
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<GameEvent> {
+        self.events.subscribe()
+    }
+
+    // polling stops for good once the process is gone
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl<P: ProcessMemory> Drop for MemoryWatcher<P> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
\ No newline at end of file
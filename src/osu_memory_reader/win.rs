@@ -1,18 +1,25 @@
 use windows::Win32::System::Diagnostics::ToolHelp::{ CreateToolhelp32Snapshot, Process32First, Process32Next, TH32CS_SNAPPROCESS, PROCESSENTRY32 };
 use windows::Win32::System::Diagnostics::Debug::ReadProcessMemory;
-use windows::Win32::Foundation::{ CloseHandle, MAX_PATH, HANDLE, HMODULE };
-use windows::Win32::System::Threading::{ OpenProcess, PROCESS_VM_READ, PROCESS_QUERY_INFORMATION };
+use windows::Win32::Foundation::{ CloseHandle, MAX_PATH, HANDLE, HMODULE, FILETIME, E_ACCESSDENIED };
+use windows::Win32::System::Threading::{ OpenProcess, GetProcessTimes, GetExitCodeProcess, PROCESS_VM_READ, PROCESS_QUERY_INFORMATION };
 use windows::Win32::System::ProcessStatus::{ EnumProcessModules, MODULEINFO, GetModuleFileNameExA, GetModuleInformation };
-use windows::Win32::System::Memory::{ VirtualQueryEx, MEMORY_BASIC_INFORMATION };
+use windows::Win32::System::Memory::{ VirtualQueryEx, MEMORY_BASIC_INFORMATION, PAGE_NOACCESS, PAGE_GUARD };
+
+use crate::osu_memory_reader::error::MemoryError;
+use crate::osu_memory_reader::process::MemoryRegion;
 
 use std::cmp::Ordering;
 use std::ffi::c_void;
 use std::mem::size_of;
+use std::path::{ Path, PathBuf };
 use std::ptr::null_mut;
 use std::slice;
 use std::str;
 use std::str::Utf8Error;
 
+pub type ProcessHandle = HANDLE;
+pub type ModuleInfo = MODULEINFO;
+
 unsafe fn path_as_str(chars: &[u8; MAX_PATH as usize]) -> Result<&str, Utf8Error> {
     let mut len: usize = 0;
     for c in chars {
@@ -24,25 +31,34 @@ unsafe fn path_as_str(chars: &[u8; MAX_PATH as usize]) -> Result<&str, Utf8Error
     str::from_utf8(slice::from_raw_parts(chars.as_ptr(), len))
 }
 
-pub unsafe fn close_handle(handle: HANDLE) -> Result<(), String> {
+pub unsafe fn close_handle(handle: HANDLE) -> Result<(), MemoryError> {
     if let Err(e) = CloseHandle(handle) {
-        return Err(format!("Failed to close snapshot handle: {}", e.message()));
+        return Err(MemoryError::Os(format!("Failed to close snapshot handle: {}", e.message())));
     }
     Ok(())
 }
 
-pub unsafe fn open_process(pid: u32) -> Result<HANDLE, String> {
+pub unsafe fn open_process(pid: u32) -> Result<HANDLE, MemoryError> {
     let hproc = OpenProcess(PROCESS_VM_READ | PROCESS_QUERY_INFORMATION, None, pid);
     if let Err(e) = hproc {
-        return Err(format!("Failed to open process from pid: {}", e.message()));
+        if e.code() == E_ACCESSDENIED {
+            return Err(MemoryError::AccessDenied { pid, message: e.message().to_string() });
+        }
+        return Err(MemoryError::Os(format!("Failed to open process from pid: {}", e.message())));
     }
     Ok(hproc.unwrap())
 }
 
-pub unsafe fn find_proc(proc_name: &str) -> Result<u32, String> {
+pub unsafe fn process_alive(hproc: HANDLE) -> bool {
+    // STILL_ACTIVE
+    let mut code: u32 = 0;
+    GetExitCodeProcess(hproc, &mut code).is_ok() && code == 259
+}
+
+pub unsafe fn find_procs(proc_name: &str) -> Result<Vec<u32>, MemoryError> {
     let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
     if let Err(e) = snapshot {
-        return Err(format!("Received invalid snapshot handle: {}", e.message()));
+        return Err(MemoryError::Os(format!("Received invalid snapshot handle: {}", e.message())));
     }
     let snapshot = snapshot.unwrap();
 
@@ -50,48 +66,42 @@ pub unsafe fn find_proc(proc_name: &str) -> Result<u32, String> {
     pe.dwSize = size_of::<PROCESSENTRY32>() as u32;
     let mut result = Process32First(snapshot, &mut pe);
 
+    let mut pids = Vec::new();
     while let Ok(()) = result {
         let file_name = match path_as_str(&pe.szExeFile) {
             Ok(s) => s,
-            Err(e) => return Err(format!("Failed to parse szExeFile: {}", e))
+            Err(e) => {
+                let _ = close_handle(snapshot);
+                return Err(MemoryError::Os(format!("Failed to parse szExeFile: {}", e)));
+            }
         };
         if let Ordering::Equal = proc_name.cmp(&file_name) {
-            return match close_handle(snapshot) {
-                Err(e) => Err(e),
-                Ok(()) => Ok(pe.th32ProcessID)
-            };
+            pids.push(pe.th32ProcessID);
         }
         result = Process32Next(snapshot, &mut pe);
     }
 
-    match close_handle(snapshot) {
-        Err(e) => Err(e),
-        Ok(()) => {
-            match result {
-                Ok(()) => Err(String::from("Failed to find process")),
-                Err(e) => Err(format!("Failed to iterate processes: {}", e.message()))
-            }
-        }
-    }
+    close_handle(snapshot)?;
+    Ok(pids)
 }
 
-unsafe fn get_module_info(hproc: HANDLE, h_mod: HMODULE) -> Result<MODULEINFO, String> {
+unsafe fn get_module_info(hproc: HANDLE, h_mod: HMODULE) -> Result<MODULEINFO, MemoryError> {
     let mut module_info: MODULEINFO = Default::default();
     if let Err(e) = GetModuleInformation(hproc, h_mod, &mut module_info, size_of::<MODULEINFO>() as u32) {
-        return Err(format!("Failed to get module information: {}", e.message()));
+        return Err(MemoryError::Os(format!("Failed to get module information: {}", e.message())));
     }
     Ok(module_info)
 }
 
-pub unsafe fn get_proc_info(hproc: HANDLE) -> Result<(String, MODULEINFO), String> {
+pub unsafe fn get_proc_info(hproc: HANDLE) -> Result<(String, MODULEINFO), MemoryError> {
     let mut lpcb_needed: u32 = 0;
     if let Err(e) = EnumProcessModules(hproc, null_mut(), 0, &mut lpcb_needed) {
-        return Err(format!("Failed to get lpcb_needed: {}", e.message()));
+        return Err(MemoryError::Os(format!("Failed to get lpcb_needed: {}", e.message())));
     }
 
     let mut modules: Vec<HMODULE> = Vec::with_capacity((lpcb_needed as usize)/size_of::<HMODULE>());
     if let Err(e) = EnumProcessModules(hproc, modules.as_mut_ptr(), lpcb_needed, &mut lpcb_needed) {
-        return Err(format!("Failed to get modules: {}", e.message()));
+        return Err(MemoryError::Os(format!("Failed to get modules: {}", e.message())));
     }
     modules.set_len(modules.capacity());
 
@@ -105,7 +115,18 @@ pub unsafe fn get_proc_info(hproc: HANDLE) -> Result<(String, MODULEINFO), Strin
         }
     }
 
-    Err(String::from("Unable to find module"))
+    Err(MemoryError::Os(String::from("Unable to find module")))
+}
+
+pub unsafe fn process_start_time(hproc: HANDLE) -> Result<u64, MemoryError> {
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    if let Err(e) = GetProcessTimes(hproc, &mut creation, &mut exit, &mut kernel, &mut user) {
+        return Err(MemoryError::Os(format!("Failed to get process times: {}", e.message())));
+    }
+    Ok(((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64)
 }
 
 pub unsafe fn query_page(hproc: HANDLE, addr: usize) -> Option<MEMORY_BASIC_INFORMATION> {
@@ -116,9 +137,29 @@ pub unsafe fn query_page(hproc: HANDLE, addr: usize) -> Option<MEMORY_BASIC_INFO
     Some(info)
 }
 
-pub unsafe fn read_address(hproc: HANDLE, addr: usize, buf: &mut [u8], size: usize) -> Result<(), String> {
+pub unsafe fn readable_regions(hproc: HANDLE) -> Result<Vec<MemoryRegion>, MemoryError> {
+    let mut regions = Vec::new();
+    let mut addr = 0;
+    while let Some(page) = query_page(hproc, addr) {
+        let base = page.BaseAddress as usize;
+        addr = base + page.RegionSize;
+
+        if page.Protect.0 & PAGE_NOACCESS.0 > 0 || page.Protect.0 & PAGE_GUARD.0 > 0 || page.Protect.0 == 0 {
+            continue;
+        }
+
+        regions.push(MemoryRegion { base, size: page.RegionSize });
+    }
+    Ok(regions)
+}
+
+pub unsafe fn read_address(hproc: HANDLE, addr: usize, buf: &mut [u8], size: usize) -> Result<(), MemoryError> {
     if let Err(e) = ReadProcessMemory(hproc, addr as *const c_void, buf.as_mut_ptr().cast(), size, None) {
-        return Err(format!("Failed to read address {:X}: {}", addr, e.message()));
+        return Err(MemoryError::ReadFailed { addr, message: e.message().to_string() });
     }
     Ok(())
+}
+
+pub fn native_path(osu_path: &Path, path: &str) -> PathBuf {
+    osu_path.join(path)
 }
\ No newline at end of file